@@ -4,22 +4,50 @@ extern crate alloc;
 use alloc::vec::Vec;
 
 use openzeppelin_stylus::{
-    access::ownable::{self, IOwnable, Ownable},
+    access::{
+        control::{self, AccessControl, IAccessControl},
+        ownable::{self, IOwnable, Ownable},
+    },
     token::erc20::{
         self,
         extensions::{capped, Capped, Erc20Metadata, ICapped, IErc20Burnable, IErc20Metadata},
         Erc20, IErc20,
     },
-    utils::introspection::erc165::IErc165,
+    utils::{
+        introspection::erc165::IErc165,
+        pausable::{self, IPausable, Pausable},
+    },
 };
 use stylus_sdk::{
-    alloy_primitives::{aliases::B32, uint, Address, U256, U8},
+    alloy_primitives::{aliases::B32, uint, Address, B256, U256, U8},
+    alloy_sol_types::sol,
     prelude::*,
     storage::{StorageAddress, StorageBool},
 };
 
+sol_interface! {
+    interface ILottery {
+        function lotteryEntryFee() external view returns (uint256);
+        function registerTokenEntry(address participant) external;
+    }
+}
+
 const DECIMALS: U8 = uint!(10_U8); // 10
 
+// Role identifiers, matching Solidity's `keccak256("MINTER_ROLE")` etc.
+const DEFAULT_ADMIN_ROLE: [u8; 32] = AccessControl::DEFAULT_ADMIN_ROLE;
+const MINTER_ROLE: [u8; 32] = keccak_const::Keccak256::new()
+    .update(b"MINTER_ROLE")
+    .finalize();
+const PAUSER_ROLE: [u8; 32] = keccak_const::Keccak256::new()
+    .update(b"PAUSER_ROLE")
+    .finalize();
+
+sol! {
+    #[derive(Debug)]
+    error LotteryEntryFailed(address lottery, address participant);
+}
+
 #[derive(SolidityError, Debug)]
 enum Error {
     ExceededCap(capped::ERC20ExceededCap),
@@ -30,8 +58,13 @@ enum Error {
     InsufficientAllowance(erc20::ERC20InsufficientAllowance),
     InvalidSpender(erc20::ERC20InvalidSpender),
     InvalidApprover(erc20::ERC20InvalidApprover),
-    UnauthorizedAccount(ownable::OwnableUnauthorizedAccount),
+    UnauthorizedAccount(control::AccessControlUnauthorizedAccount),
+    BadConfirmation(control::AccessControlBadConfirmation),
+    OwnableUnauthorizedAccount(ownable::OwnableUnauthorizedAccount),
     InvalidOwner(ownable::OwnableInvalidOwner),
+    EnforcedPause(pausable::EnforcedPause),
+    ExpectedPause(pausable::ExpectedPause),
+    LotteryEntryFailed(LotteryEntryFailed),
 }
 
 impl From<capped::Error> for Error {
@@ -56,15 +89,33 @@ impl From<erc20::Error> for Error {
     }
 }
 
+impl From<control::Error> for Error {
+    fn from(value: control::Error) -> Self {
+        match value {
+            control::Error::UnauthorizedAccount(e) => Error::UnauthorizedAccount(e),
+            control::Error::BadConfirmation(e) => Error::BadConfirmation(e),
+        }
+    }
+}
+
 impl From<ownable::Error> for Error {
     fn from(value: ownable::Error) -> Self {
         match value {
-            ownable::Error::UnauthorizedAccount(e) => Error::UnauthorizedAccount(e),
+            ownable::Error::UnauthorizedAccount(e) => Error::OwnableUnauthorizedAccount(e),
             ownable::Error::InvalidOwner(e) => Error::InvalidOwner(e),
         }
     }
 }
 
+impl From<pausable::Error> for Error {
+    fn from(value: pausable::Error) -> Self {
+        match value {
+            pausable::Error::EnforcedPause(e) => Error::EnforcedPause(e),
+            pausable::Error::ExpectedPause(e) => Error::ExpectedPause(e),
+        }
+    }
+}
+
 #[entrypoint]
 #[storage]
 struct Erc20Token {
@@ -72,8 +123,11 @@ struct Erc20Token {
     metadata: Erc20Metadata,
     capped: Capped,
     ownable: Ownable,
-    authorized_minter: StorageAddress,
+    access: AccessControl,
+    pausable: Pausable,
     minting: StorageBool,
+    lottery_address: StorageAddress,
+    entering_lottery: StorageBool,
 }
 
 #[public]
@@ -90,6 +144,10 @@ impl Erc20Token {
         self.metadata.constructor(name, symbol);
         self.capped.constructor(cap)?;
         self.ownable.constructor(owner)?;
+        // The deployer-supplied owner becomes the default admin and holds every role initially
+        self.access._grant_role(DEFAULT_ADMIN_ROLE.into(), owner);
+        self.access._grant_role(MINTER_ROLE.into(), owner);
+        self.access._grant_role(PAUSER_ROLE.into(), owner);
         Ok(())
     }
 
@@ -106,16 +164,15 @@ impl Erc20Token {
         }
         self.minting.set(true);
 
-        // Allow either owner or authorized minter to mint
+        // Only accounts holding MINTER_ROLE can mint
         let caller = self.vm().msg_sender();
-        let owner = self.ownable.owner();
-        let authorized = self.authorized_minter.get();
-        
-        if caller != owner && (authorized == Address::ZERO || caller != authorized) {
+        if let Err(e) = self.access._check_role(MINTER_ROLE.into(), caller) {
             self.minting.set(false);
-            return Err(ownable::Error::UnauthorizedAccount(ownable::OwnableUnauthorizedAccount {
-                account: caller,
-            }))?;
+            return Err(e)?;
+        }
+        if let Err(e) = self.pausable.when_not_paused() {
+            self.minting.set(false);
+            return Err(e)?;
         }
 
         let max_supply = self.capped.cap();
@@ -151,7 +208,10 @@ impl Erc20Token {
     }
 
     pub fn transfer(&mut self, to: Address, value: U256) -> Result<bool, Error> {
-        Ok(self.erc20.transfer(to, value)?)
+        self.pausable.when_not_paused()?;
+        let transferred = self.erc20.transfer(to, value)?;
+        self.enter_lottery_on_transfer(self.vm().msg_sender(), to, value)?;
+        Ok(transferred)
     }
 
     pub fn allowance(&self, owner: Address, spender: Address) -> U256 {
@@ -168,15 +228,18 @@ impl Erc20Token {
         to: Address,
         value: U256,
     ) -> Result<bool, Error> {
+        self.pausable.when_not_paused()?;
         Ok(self.erc20.transfer_from(from, to, value)?)
     }
 
     // IErc20Burnable trait implementations
     pub fn burn(&mut self, value: U256) -> Result<(), Error> {
+        self.pausable.when_not_paused()?;
         Ok(self.erc20.burn(value)?)
     }
 
     pub fn burn_from(&mut self, account: Address, value: U256) -> Result<(), Error> {
+        self.pausable.when_not_paused()?;
         Ok(self.erc20.burn_from(account, value)?)
     }
 
@@ -202,16 +265,132 @@ impl Erc20Token {
     pub fn supports_interface(&self, interface_id: B32) -> bool {
         Erc20::supports_interface(&self.erc20, interface_id)
             || Erc20Metadata::supports_interface(&self.metadata, interface_id)
+            || AccessControl::supports_interface(&self.access, interface_id)
+    }
+
+    // IAccessControl trait implementations
+    pub fn has_role(&self, role: B256, account: Address) -> bool {
+        self.access.has_role(role, account)
+    }
+
+    pub fn get_role_admin(&self, role: B256) -> B256 {
+        self.access.get_role_admin(role)
+    }
+
+    pub fn grant_role(&mut self, role: B256, account: Address) -> Result<(), Error> {
+        Ok(self.access.grant_role(role, account)?)
+    }
+
+    pub fn revoke_role(&mut self, role: B256, account: Address) -> Result<(), Error> {
+        Ok(self.access.revoke_role(role, account)?)
+    }
+
+    pub fn renounce_role(&mut self, role: B256, confirmation: Address) -> Result<(), Error> {
+        Ok(self.access.renounce_role(role, confirmation)?)
+    }
+
+    pub fn minter_role(&self) -> B256 {
+        MINTER_ROLE.into()
+    }
+
+    pub fn pauser_role(&self) -> B256 {
+        PAUSER_ROLE.into()
     }
 
-    // Authorized minter getter and setter
-    pub fn authorized_minter(&self) -> Address {
-        self.authorized_minter.get()
+    // IPausable trait implementations, gated on PAUSER_ROLE
+    pub fn paused(&self) -> bool {
+        self.pausable.paused()
     }
 
-    pub fn set_authorized_minter(&mut self, minter: Address) -> Result<(), Error> {
-        self.ownable.only_owner()?;
-        self.authorized_minter.set(minter);
+    pub fn pause(&mut self) -> Result<(), Error> {
+        self.access.only_role(PAUSER_ROLE.into())?;
+        Ok(self.pausable.pause()?)
+    }
+
+    pub fn unpause(&mut self) -> Result<(), Error> {
+        self.access.only_role(PAUSER_ROLE.into())?;
+        Ok(self.pausable.unpause()?)
+    }
+
+    // IOwnable trait implementations. The owner's roles move with ownership, so a
+    // previous owner keeps no admin, minter or pauser rights.
+    pub fn owner(&self) -> Address {
+        self.ownable.owner()
+    }
+
+    pub fn transfer_ownership(&mut self, new_owner: Address) -> Result<(), Error> {
+        let previous_owner = self.ownable.owner();
+        self.ownable.transfer_ownership(new_owner)?;
+        self.hand_over_roles(previous_owner, new_owner);
+        Ok(())
+    }
+
+    pub fn renounce_ownership(&mut self) -> Result<(), Error> {
+        let previous_owner = self.ownable.owner();
+        self.ownable.renounce_ownership()?;
+        self.hand_over_roles(previous_owner, Address::ZERO);
+        Ok(())
+    }
+
+    // Lottery auto-entry getter and setter (zero address disables it)
+    pub fn lottery_address(&self) -> Address {
+        self.lottery_address.get()
+    }
+
+    pub fn set_lottery_address(&mut self, lottery: Address) -> Result<(), Error> {
+        self.access.only_role(DEFAULT_ADMIN_ROLE.into())?;
+        self.lottery_address.set(lottery);
+        Ok(())
+    }
+}
+
+impl Erc20Token {
+    // Revoke each role `from` holds and grant it to `to`; a zero `to` (renounced
+    // ownership) only revokes.
+    fn hand_over_roles(&mut self, from: Address, to: Address) {
+        for role in [DEFAULT_ADMIN_ROLE, MINTER_ROLE, PAUSER_ROLE] {
+            if self.access._revoke_role(role.into(), from) && to != Address::ZERO {
+                self.access._grant_role(role.into(), to);
+            }
+        }
+    }
+
+    // Register `from` in the lottery when it transfers exactly the entry fee to it.
+    //
+    // The transfer is reverted if the lottery rejects the entry, so tokens are
+    // never sent without a matching participation.
+    fn enter_lottery_on_transfer(
+        &mut self,
+        from: Address,
+        to: Address,
+        value: U256,
+    ) -> Result<(), Error> {
+        let lottery_address = self.lottery_address.get();
+        if lottery_address == Address::ZERO
+            || to != lottery_address
+            || self.entering_lottery.get()
+        {
+            return Ok(());
+        }
+
+        let lottery = ILottery::new(lottery_address);
+        let entry_fee = lottery.lottery_entry_fee(&mut *self);
+        if entry_fee.ok() != Some(value) {
+            return Ok(());
+        }
+
+        self.entering_lottery.set(true);
+        let result = lottery.register_token_entry(&mut *self, from);
+        self.entering_lottery.set(false);
+        result.map_err(|_| {
+            Error::LotteryEntryFailed(LotteryEntryFailed {
+                lottery: lottery_address,
+                participant: from,
+            })
+        })?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,271 @@
+//! Native unit tests on the Stylus `TestVM`
+//!
+//! Calls to other contracts are answered by `mock_external`
+
+use super::*;
+use stylus_sdk::alloy_sol_types::SolCall;
+use stylus_sdk::testing::*;
+
+// Native stand-ins for the host imports that code outside `TestVM` links against: `sol_interface!`
+// calls and OpenZeppelin's deprecated `msg::sender`/`evm::log` bypass the VM. Calls are answered
+// from `mock_external` (unmocked ones revert) and logged for `external_calls`; logs are dropped
+mod host_stubs {
+    use super::Address;
+    use core::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+
+    type Mocks = HashMap<(Address, Vec<u8>), Result<Vec<u8>, Vec<u8>>>;
+
+    std::thread_local! {
+        pub static SENDER: Cell<[u8; 20]> = const { Cell::new([0; 20]) };
+        pub static MOCKS: RefCell<Mocks> = RefCell::new(HashMap::new());
+        pub static CALLS: RefCell<Vec<(Address, Vec<u8>)>> = const { RefCell::new(Vec::new()) };
+        static RETURN_DATA: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// # Safety
+    /// `contract` must point to 20 bytes and `calldata` to `calldata_len` bytes
+    unsafe fn answer(contract: *const u8, calldata: *const u8, calldata_len: usize, outs: *mut usize) -> u8 {
+        let to = Address::from_slice(core::slice::from_raw_parts(contract, 20));
+        let data = core::slice::from_raw_parts(calldata, calldata_len).to_vec();
+        CALLS.with(|calls| calls.borrow_mut().push((to, data.clone())));
+        let result = MOCKS.with(|mocks| mocks.borrow().get(&(to, data)).cloned()).unwrap_or(Err(Vec::new()));
+        let (status, returned) = match result {
+            Ok(returned) => (0, returned),
+            Err(returned) => (1, returned),
+        };
+        *outs = returned.len();
+        RETURN_DATA.with(|data| *data.borrow_mut() = returned);
+        status
+    }
+
+    #[no_mangle]
+    extern "C" fn msg_sender(sender: *mut u8) {
+        let bytes = SENDER.with(Cell::get);
+        // SAFETY: the SDK passes a 20-byte output buffer
+        unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), sender, 20) };
+    }
+
+    #[no_mangle]
+    extern "C" fn emit_log(_data: *const u8, _len: usize, _topics: usize) {}
+
+    #[no_mangle]
+    extern "C" fn storage_flush_cache(_clear: bool) {}
+
+    #[no_mangle]
+    unsafe extern "C" fn call_contract(
+        contract: *const u8,
+        calldata: *const u8,
+        calldata_len: usize,
+        _value: *const u8,
+        _gas: u64,
+        return_data_len: *mut usize,
+    ) -> u8 {
+        answer(contract, calldata, calldata_len, return_data_len)
+    }
+
+    #[no_mangle]
+    extern "C" fn delegate_call_contract(
+        _contract: *const u8,
+        _calldata: *const u8,
+        _calldata_len: usize,
+        _gas: u64,
+        _return_data_len: *mut usize,
+    ) -> u8 {
+        panic!("delegate calls are not supported in tests")
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn static_call_contract(
+        contract: *const u8,
+        calldata: *const u8,
+        calldata_len: usize,
+        _gas: u64,
+        return_data_len: *mut usize,
+    ) -> u8 {
+        answer(contract, calldata, calldata_len, return_data_len)
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn read_return_data(dest: *mut u8, offset: usize, size: usize) -> usize {
+        RETURN_DATA.with(|data| {
+            let data = data.borrow();
+            let available = data.get(offset..).unwrap_or_default();
+            let copied = available.len().min(size);
+            core::ptr::copy_nonoverlapping(available.as_ptr(), dest, copied);
+            copied
+        })
+    }
+
+    #[no_mangle]
+    extern "C" fn return_data_size() -> usize {
+        RETURN_DATA.with(|data| data.borrow().len())
+    }
+}
+
+/// Answer calls from the token to `to` with exactly `calldata`
+fn mock_external(to: Address, calldata: Vec<u8>, result: Result<Vec<u8>, Vec<u8>>) {
+    host_stubs::MOCKS.with(|mocks| mocks.borrow_mut().insert((to, calldata), result));
+}
+
+/// Calls the token made through `sol_interface!` bindings, as (to, calldata)
+fn external_calls() -> Vec<(Address, Vec<u8>)> {
+    host_stubs::CALLS.with(|calls| calls.borrow().clone())
+}
+
+const OWNER: Address = Address::new([0x11; 20]);
+const ALICE: Address = Address::new([0xa1; 20]);
+const BOB: Address = Address::new([0xb0; 20]);
+const LOTTERY: Address = Address::new([0x1e; 20]);
+
+const CAP: u64 = 1_000_000;
+const FEE: u64 = 100;
+
+// Calldata of the lottery calls made on transfers to `lottery_address`
+mod lottery_calls {
+    stylus_sdk::alloy_sol_types::sol! {
+        function lotteryEntryFee() external view returns (uint256);
+        function registerTokenEntry(address participant) external;
+    }
+}
+
+fn setup() -> (TestVM, Erc20Token) {
+    let vm = TestVM::default();
+    act_as(&vm, OWNER);
+    let mut token = Erc20Token::from(&vm);
+    token.constructor("Lottery".into(), "LOT".into(), U256::from(CAP), OWNER).unwrap();
+    (vm, token)
+}
+
+/// Make `who` the caller for both `TestVM` and the deprecated global `msg::sender`
+fn act_as(vm: &TestVM, who: Address) {
+    vm.set_sender(who);
+    host_stubs::SENDER.with(|sender| sender.set(who.into_array()));
+}
+
+#[test]
+fn deployer_is_owner_admin_minter_and_pauser() {
+    let (_vm, token) = setup();
+    assert_eq!(token.owner(), OWNER);
+    assert!(token.has_role(DEFAULT_ADMIN_ROLE.into(), OWNER));
+    assert!(token.has_role(token.minter_role(), OWNER));
+    assert!(token.has_role(token.pauser_role(), OWNER));
+    assert_eq!(token.get_role_admin(token.minter_role()), B256::from(DEFAULT_ADMIN_ROLE));
+}
+
+#[test]
+fn only_the_admin_grants_roles() {
+    let (vm, mut token) = setup();
+    let minter = token.minter_role();
+    act_as(&vm, ALICE);
+    assert!(matches!(token.grant_role(minter, BOB), Err(Error::UnauthorizedAccount(_))));
+    assert!(!token.has_role(minter, BOB));
+
+    act_as(&vm, OWNER);
+    token.grant_role(minter, ALICE).unwrap();
+    assert!(token.has_role(minter, ALICE));
+}
+
+#[test]
+fn minting_requires_the_minter_role() {
+    let (vm, mut token) = setup();
+    act_as(&vm, ALICE);
+    assert!(matches!(token.mint(ALICE, U256::from(10)), Err(Error::UnauthorizedAccount(_))));
+    assert_eq!(token.total_supply(), U256::ZERO);
+
+    act_as(&vm, OWNER);
+    token.grant_role(token.minter_role(), ALICE).unwrap();
+    act_as(&vm, ALICE);
+    token.mint(BOB, U256::from(10)).unwrap();
+    assert_eq!(token.balance_of(BOB), U256::from(10));
+    assert!(matches!(token.mint(BOB, U256::from(CAP)), Err(Error::ExceededCap(_))));
+}
+
+#[test]
+fn revoked_minters_can_no_longer_mint() {
+    let (vm, mut token) = setup();
+    let minter = token.minter_role();
+    token.grant_role(minter, ALICE).unwrap();
+    act_as(&vm, ALICE);
+    token.mint(ALICE, U256::from(1)).unwrap();
+
+    act_as(&vm, OWNER);
+    token.revoke_role(minter, ALICE).unwrap();
+    assert!(!token.has_role(minter, ALICE));
+    act_as(&vm, ALICE);
+    assert!(matches!(token.mint(ALICE, U256::from(1)), Err(Error::UnauthorizedAccount(_))));
+    assert_eq!(token.balance_of(ALICE), U256::from(1));
+}
+
+#[test]
+fn pausers_freeze_transfers_and_minting() {
+    let (vm, mut token) = setup();
+    token.mint(ALICE, U256::from(10)).unwrap();
+    act_as(&vm, ALICE);
+    assert!(matches!(token.pause(), Err(Error::UnauthorizedAccount(_))));
+
+    act_as(&vm, OWNER);
+    token.pause().unwrap();
+    assert!(token.paused());
+    assert!(matches!(token.mint(ALICE, U256::from(1)), Err(Error::EnforcedPause(_))));
+    act_as(&vm, ALICE);
+    assert!(matches!(token.transfer(BOB, U256::from(1)), Err(Error::EnforcedPause(_))));
+
+    act_as(&vm, OWNER);
+    token.unpause().unwrap();
+    act_as(&vm, ALICE);
+    token.transfer(BOB, U256::from(1)).unwrap();
+    assert_eq!(token.balance_of(BOB), U256::from(1));
+}
+
+#[test]
+fn ownership_carries_the_owner_roles() {
+    let (vm, mut token) = setup();
+    let roles = [B256::from(DEFAULT_ADMIN_ROLE), token.minter_role(), token.pauser_role()];
+    act_as(&vm, ALICE);
+    assert!(matches!(token.transfer_ownership(ALICE), Err(Error::OwnableUnauthorizedAccount(_))));
+
+    act_as(&vm, OWNER);
+    token.transfer_ownership(ALICE).unwrap();
+    assert_eq!(token.owner(), ALICE);
+    for role in roles {
+        assert!(token.has_role(role, ALICE));
+        assert!(!token.has_role(role, OWNER));
+    }
+    assert!(matches!(token.mint(OWNER, U256::from(1)), Err(Error::UnauthorizedAccount(_))));
+
+    // Renouncing leaves nobody with the owner's roles
+    act_as(&vm, ALICE);
+    token.renounce_ownership().unwrap();
+    assert_eq!(token.owner(), Address::ZERO);
+    for role in roles {
+        assert!(!token.has_role(role, ALICE));
+    }
+}
+
+#[test]
+fn transferring_the_entry_fee_to_the_lottery_registers_the_sender() {
+    let (vm, mut token) = setup();
+    token.mint(ALICE, U256::from(2 * FEE)).unwrap();
+    token.mint(BOB, U256::from(FEE)).unwrap();
+    act_as(&vm, ALICE);
+    assert!(matches!(token.set_lottery_address(LOTTERY), Err(Error::UnauthorizedAccount(_))));
+    act_as(&vm, OWNER);
+    token.set_lottery_address(LOTTERY).unwrap();
+    let fee = U256::from(FEE).to_be_bytes::<32>().to_vec();
+    mock_external(LOTTERY, lottery_calls::lotteryEntryFeeCall {}.abi_encode(), Ok(fee));
+    let register = lottery_calls::registerTokenEntryCall { participant: ALICE }.abi_encode();
+    mock_external(LOTTERY, register.clone(), Ok(Vec::new()));
+
+    // Any other amount is a plain transfer
+    act_as(&vm, ALICE);
+    token.transfer(LOTTERY, U256::from(FEE - 1)).unwrap();
+    assert!(!external_calls().iter().any(|(_, data)| *data == register));
+    token.transfer(LOTTERY, U256::from(FEE)).unwrap();
+    assert!(external_calls().contains(&(LOTTERY, register)));
+    assert_eq!(token.balance_of(LOTTERY), U256::from(2 * FEE - 1));
+
+    // The lottery rejecting the entry (unmocked here) fails the transfer
+    act_as(&vm, BOB);
+    assert!(matches!(token.transfer(LOTTERY, U256::from(FEE)), Err(Error::LotteryEntryFailed(_))));
+}
@@ -14,7 +14,17 @@ const envPath = path.resolve(__dirname, "../.env");
 if (fs.existsSync(envPath)) {
   dotenvConfig({ path: envPath });
 }
-import { Abi, Account, createPublicClient, createWalletClient, http, PublicClient, WalletClient } from "viem";
+import {
+  Abi,
+  Account,
+  createPublicClient,
+  createWalletClient,
+  http,
+  keccak256,
+  PublicClient,
+  toBytes,
+  WalletClient,
+} from "viem";
 import { privateKeyToAccount } from "viem/accounts";
 
 /**
@@ -42,6 +52,7 @@ export default async function deployScript(deployOptions: DeployOptions) {
     constructorArgs: [
       "0x29576aB8152A09b9DC634804e4aDE73dA1f3a3CC", // Hardcoded Arbitrum Sepolia VRF V2+ Wrapper address
       config.deployerAddress!,
+      "VRF Lottery", // Informational label, renameable later via `set_lottery_name`
     ],
     ...deployOptions,
   });
@@ -77,14 +88,14 @@ export default async function deployScript(deployOptions: DeployOptions) {
     await executeContractFunction({
       contractName: erc20ContractName,
       contractAddress: erc20Deployment.address,
-      functionName: "setAuthorizedMinter",
-      args: [vrfDeployment.address],
+      functionName: "grantRole",
+      args: [keccak256(toBytes("MINTER_ROLE")), vrfDeployment.address],
       account,
       publicClient,
       walletClient,
       chainId: config.chain.id.toString(),
-      successMessage: `Authorized minter set to ${vrfDeployment.address}`,
-      errorMessage: "Failed to set authorized minter",
+      successMessage: `Minter role granted to ${vrfDeployment.address}`,
+      errorMessage: "Failed to grant minter role",
     });    
     await executeContractFunction({
       contractName: vrfContractName,
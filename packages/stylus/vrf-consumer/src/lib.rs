@@ -11,15 +11,19 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 #![cfg_attr(not(any(test, feature = "export-abi")), no_std)]
 
+// Injected randomness is for native tests only and must never reach a deployable build
+#[cfg(all(feature = "test-mode", target_arch = "wasm32"))]
+compile_error!("the `test-mode` feature cannot be enabled for wasm32 (deployable) builds");
+
 #[macro_use]
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 /// Import items from the SDK. The prelude contains common traits and macros.
 use stylus_sdk::{
-    alloy_primitives::{Address, Bytes, U16, U256, U32},
-    alloy_sol_types::sol,
+    alloy_primitives::{Address, Bytes, FixedBytes, B256, U16, U256, U32, U8},
+    alloy_sol_types::{sol, SolValue},
     prelude::*,
     stylus_core::calls::context::Call,
     stylus_core::log,
@@ -31,6 +35,10 @@ use stylus_sdk::call::Call as OldCall;
 
 /// Import OpenZeppelin Ownable functionality
 use openzeppelin_stylus::access::ownable::{self, Ownable};
+#[cfg(feature = "signed-actions")]
+use openzeppelin_stylus::utils::cryptography::{ecdsa, eip712::IEip712};
+#[cfg(feature = "signed-actions")]
+use stylus_sdk::alloy_sol_types::SolCall;
 
 // RequestData struct removed - we only store last fulfilled request now
 
@@ -48,22 +56,221 @@ sol_storage! {
         uint256 callback_gas_limit;
         uint256 request_confirmations;
         uint256 num_words;
+        uint256 max_num_words;
+        uint256 max_request_price;
+        uint256 keeper_reward;
+        uint256 min_log_value;
 
         Ownable ownable;
-        bool withdrawing;
+        // Informational label telling lottery instances apart
+        string lottery_name;
+        // Packed boolean flags, see the `FLAG_*` bits
+        uint256 flags;
 
         // Event variables
-        bool accepting_participants;
         uint256 lottery_interval_hours; 
         uint256 last_request_timestamp;
+        uint256 genesis_timestamp;
+        uint256 initial_grace_seconds;
 
         // Token distribution variables
         address erc20_token_address;
+        uint256 reward_token_decimals;
         address[] participants;
         uint256 lottery_entry_fee;
+        uint256 min_participants;
+        uint256 max_participants;
+        // Anti-sybil gate: reward tokens an entrant must hold, zero disables it
+        uint256 min_token_balance_to_enter;
+        // Native balance entries and deposits may not push the contract above, zero disables it
+        uint256 max_contract_balance;
+        // Native balance owner withdrawals may not drop the contract below, zero disables it
+        uint256 min_operational_balance;
+        uint256 entry_fee_native;
+        address price_feed;
+        uint256 entry_burn_bps;
+        // Prize split: winner and treasury shares are minted, the burn share is never minted
+        uint256 winner_bps;
+        uint256 treasury_bps;
+        uint256 burn_bps;
+        address treasury;
+        // Operational cut of each native pool credited to the treasury at draw time
+        uint256 auto_withdraw_bps;
+        uint256 total_auto_withdrawn;
+        // Optional `isEligible(address)` check re-run on the selected winner at draw time
+        address winner_eligibility_hook;
+        // Undistributed prize remainder rolled into the next round when auto-compounding
+        uint256 carry_over;
+        uint256 fixed_reward_amount;
+        mapping(address => uint256) pending_rewards;
+        mapping(address => uint256) pending_refunds;
+        // Sum of `pending_refunds`: ETH owed to claimants that `sweep_dust` must leave alone
+        uint256 total_pending_refunds;
+        // Token refunds that couldn't be pushed, keyed by token then account
+        mapping(address => mapping(address => uint256)) pending_token_refunds;
+        // Sum of `pending_token_refunds` per token, which `burn_pool` must leave alone
+        mapping(address => uint256) total_pending_token_refunds;
+        // Secondary prizes per draw, committed as a Merkle root of (account, amount) leaves
+        mapping(uint256 => bytes32) prize_merkle_root;
+        mapping(uint256 => mapping(address => bool)) merkle_prize_claimed;
+        // Streamed prizes: the winner's share vests linearly over `reward_stream_duration`
+        uint256 reward_stream_duration;
+        mapping(address => uint256) stream_total;
+        mapping(address => uint256) stream_claimed;
+        mapping(address => uint256) stream_start;
+        mapping(address => uint256) stream_duration;
+        mapping(address => uint256) s_entry_time;
+        mapping(address => uint256) last_entry_block;
+        // Lifetime winnings per address
+        mapping(address => uint256) total_won;
+        mapping(address => uint256) win_count;
+        uint256 entry_cooldown_seconds;
+
+        // Ticketed entries: Fenwick tree of ticket counts keyed by 1-based participant position,
+        // only maintained with the `ticket-tree` feature
+        mapping(uint256 => uint256) ticket_tree;
+        mapping(address => uint256) ticket_count;
+        mapping(address => uint256) participant_position;
+        uint256 total_tickets;
+
+        // Fee tiers: a tier entry pays `tier_fee` and holds `tier_weight` tickets
+        mapping(uint8 => uint256) tier_fee;
+        mapping(uint8 => uint256) tier_weight;
+        // What each entrant paid and in which asset (zero for native), refunded in kind on removal
+        mapping(address => uint256) entry_paid;
+        mapping(address => address) entry_asset;
+        // Running total of what the current round's entrants paid, tiers and top-ups included,
+        // and the part of it paid in ETH
+        uint256 current_pool;
+        uint256 current_native_pool;
+        // Starts at 1 and moves on whenever the participants are cleared; an address removed by the
+        // owner is kept out until then
+        uint256 round_number;
+        mapping(address => uint256) removed_in_round;
+
+        // Request tracking
+        mapping(uint256 => uint256) s_requests_paid;
+        // Prize pool (`current_pool` at request time) behind each draw request
+        mapping(uint256 => uint256) s_request_pool;
+        mapping(uint256 => uint32) s_request_num_words;
+        mapping(uint256 => uint256) s_request_timestamp;
+        mapping(uint256 => uint256) s_fulfilled_timestamp;
+        uint256 fulfilled_count;
+        mapping(uint256 => address) s_request_forward_target;
+        // Wrapper allowed to fulfill a request made before `set_vrf_wrapper` switched wrappers
+        mapping(uint256 => address) s_request_wrapper;
+        uint256 total_fulfillment_latency;
+        uint256[] request_ids;
+        // Per-draw snapshot for `verify_draw`
+        mapping(uint256 => uint256) s_draw_random_word;
+        mapping(uint256 => uint256) s_draw_ticket_count;
+        mapping(uint256 => address) s_draw_winner;
+        mapping(uint256 => uint256) s_winner_index;
+        // Position the word landed on, eligibility re-rolls taken from it and the entrant count
+        mapping(uint256 => uint256) s_draw_drawn_index;
+        mapping(uint256 => uint256) s_draw_rerolls;
+        mapping(uint256 => uint256) s_draw_participant_count;
+        uint256 max_retained_draws;
+        uint256 pruned_draws;
+        // Above this many participants the callback only stores the words; `finalize_draw` picks the winner
+        uint256 max_fulfillment_participants;
+        // Only meaningful while `FLAG_DRAW_DEFERRED` is set, as zero is a valid request id
+        uint256 deferred_draw_id;
+
+        // Nonces for owner actions authorized by signature (`signed-actions` feature)
+        mapping(address => uint256) signed_action_nonces;
+
+        // Ring buffer of the most recent winners
+        address[] recent_winners;
+        uint256 recent_winners_head;
     }
 }
 
+// Basis point denominator for fee/reward splits
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// Entry fees are denominated in wei
+const NATIVE_DECIMALS: u8 = 18;
+
+// Participation status codes returned by `participation_status`
+// 3 (retired) and 4 (window closed) are reserved for states not tracked yet
+const STATUS_OPEN: u8 = 0;
+const STATUS_PAUSED: u8 = 1;
+const STATUS_DRAW_IN_PROGRESS: u8 = 2;
+const STATUS_FULL: u8 = 5;
+// Per-account reasons reported by `participation_quote`
+const STATUS_ALREADY_PARTICIPATING: u8 = 6;
+const STATUS_INSUFFICIENT_TOKEN_BALANCE: u8 = 7;
+const STATUS_CONTRACT_NOT_ALLOWED: u8 = 8;
+const STATUS_REMOVED: u8 = 9;
+const STATUS_COOLDOWN: u8 = 10;
+const STATUS_SAME_BLOCK: u8 = 11;
+
+// Tuple returned by `get_full_config`, field order documented there
+type FullConfig = (U256, U256, Address, U256, U256, U256, U256, U256, U256, U256, U256, U256, U256);
+
+// Bits of the packed `flags` slot
+const FLAG_ACCEPTING: u64 = 1 << 0;
+const FLAG_WITHDRAWING: u64 = 1 << 1;
+const FLAG_REQUIRE_MINTABLE_TOKEN: u64 = 1 << 2;
+const FLAG_REWARD_TOKEN_MINTABLE: u64 = 1 << 3;
+const FLAG_CLAIMING_REFUND: u64 = 1 << 4;
+const FLAG_EARLY_ENTRY_BONUS: u64 = 1 << 5;
+const FLAG_ONE_ENTRY_PER_BLOCK: u64 = 1 << 6;
+const FLAG_AUTO_COMPOUND: u64 = 1 << 7;
+const FLAG_FIXED_REWARD: u64 = 1 << 8;
+// Inverted so a zeroed slot keeps the default of auto-restarting rounds
+const FLAG_MANUAL_RESTART: u64 = 1 << 9;
+const FLAG_FREE_ENTRY: u64 = 1 << 10;
+const FLAG_PARTICIPATION_PAUSED: u64 = 1 << 11;
+const FLAG_DRAWS_PAUSED: u64 = 1 << 12;
+const FLAG_STREAM_REWARDS: u64 = 1 << 13;
+const FLAG_MIX_BLOCK_DATA: u64 = 1 << 14;
+const FLAG_REQUIRE_EOA: u64 = 1 << 15;
+const FLAG_PAY_WINNER_NATIVE: u64 = 1 << 16;
+const FLAG_DRAW_DEFERRED: u64 = 1 << 17;
+
+// EIP-712 type of an owner action relayed through `execute_signed`
+#[cfg(feature = "signed-actions")]
+const SIGNED_ACTION_TYPEHASH: [u8; 32] = keccak_const::Keccak256::new()
+    .update(b"SignedAction(bytes action,uint256 nonce)")
+    .finalize();
+
+// EIP-165 id of the consumer interface, the XOR of its draw and fulfillment selectors
+// (this ABI has no `get_request_status` to fold in)
+const CONSUMER_INTERFACE_ID: [u8; 4] = xor_selectors(
+    keccak_const::Keccak256::new().update(b"requestRandomWords()").finalize(),
+    keccak_const::Keccak256::new()
+        .update(b"rawFulfillRandomWords(uint256,uint256[])")
+        .finalize(),
+);
+const ERC165_INTERFACE_ID: [u8; 4] = [0x01, 0xff, 0xc9, 0xa7];
+
+// Floor on `lottery_interval_hours` so a misconfigured interval can't burn VRF funds on back-to-back draws
+const MIN_LOTTERY_INTERVAL_HOURS: u64 = 1;
+
+// Build identifier reported by `version`
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Heuristic fulfillment cost for `estimate_fulfillment_gas`: bookkeeping and logs, plus clearing
+// each participant's four storage slots at the end of the round
+const FULFILLMENT_BASE_GAS: u64 = 60_000;
+const FULFILLMENT_GAS_PER_PARTICIPANT: u64 = 25_000;
+
+// Bounds accepted by `set_vrf_config`, matching the VRF V2+ coordinator limits
+const MAX_CALLBACK_GAS_LIMIT: u32 = 2_500_000;
+const MAX_REQUEST_CONFIRMATIONS: u16 = 200;
+
+// Participants tried after the drawn one before a draw is left without winner
+const MAX_ELIGIBILITY_REROLLS: usize = 10;
+
+// Capacity of the recent winners ring buffer
+const RECENT_WINNERS_CAPACITY: usize = 20;
+
+// Early-entry bonus weights: the earliest entrant weighs up to 1.5x the latest
+const ENTRY_BONUS_BASE_WEIGHT: u64 = 100;
+const ENTRY_BONUS_MAX_EXTRA_WEIGHT: u64 = 50;
+
 // Define the VRF V2+ Wrapper interface
 sol_interface! {
     interface IVRFV2PlusWrapper {
@@ -74,6 +281,9 @@ sol_interface! {
             uint32 _numWords,
             bytes calldata extraArgs
         ) external payable returns (uint256 requestId);
+        // Capability flags exposed by VRFV2PlusWrapper
+        function s_configured() external view returns (bool);
+        function s_disabled() external view returns (bool);
     }
 }
 
@@ -82,24 +292,62 @@ sol_interface! {
     interface IERC20 {
         // Standard ERC20 functions
         // function totalSupply() external view returns (uint256);
-        // function balanceOf(address account) external view returns (uint256);
+        function balanceOf(address account) external view returns (uint256);
         function transfer(address to, uint256 amount) external returns (bool);
         // function allowance(address owner, address spender) external view returns (uint256);
         // function approve(address spender, uint256 amount) external returns (bool);
-        // function transferFrom(address from, address to, uint256 amount) external returns (bool);
+        function transferFrom(address from, address to, uint256 amount) external returns (bool);
         
-        // // ERC20 Burnable functions
-        // function burn(uint256 value) external;
+        // ERC20 Burnable functions
+        function burn(uint256 value) external;
         // function burnFrom(address account, uint256 value) external;
         
         // // ERC20 Metadata functions
         // function name() external view returns (string);
         // function symbol() external view returns (string);
-        // function decimals() external view returns (uint8);
+        function decimals() external view returns (uint8);
 
         // function cap() external view returns (uint256);
         // function supportsInterface(bytes4 interfaceId) external view returns (bool);
         function mint(address account, uint256 value) external;
+
+        // AccessControl, used to probe whether this contract may mint
+        function hasRole(bytes32 role, address account) external view returns (bool);
+    }
+}
+
+// ERC20 Permit (EIP-2612); the generated `permit` binding takes more args than clippy allows
+#[allow(clippy::too_many_arguments)]
+mod permit {
+    use stylus_sdk::prelude::*;
+
+    sol_interface! {
+        interface IERC20Permit {
+            function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external;
+        }
+    }
+}
+use permit::IERC20Permit;
+
+// Sub-consumer interface for randomness forwarded through `request_for`
+sol_interface! {
+    interface IRandomWordsReceiver {
+        function onRandomWords(uint256 request_id, uint256[] calldata random_words) external;
+    }
+}
+
+// Winner eligibility hook, e.g. a blacklist re-checked at draw time
+sol_interface! {
+    interface IWinnerEligibilityHook {
+        function isEligible(address account) external view returns (bool);
+    }
+}
+
+// Chainlink aggregator interface, used to price the token entry fee in native units
+sol_interface! {
+    interface IChainlinkAggregator {
+        function latestAnswer() external view returns (int256);
+        function decimals() external view returns (uint8);
     }
 }
 
@@ -108,18 +356,77 @@ sol! {
     event RequestSent(uint256 indexed requestId, uint32 numWords, uint256 payment);
     event RequestFulfilled(uint256 indexed requestId, uint256[] randomWords, address winner);
     event Received(address indexed sender, uint256 value);
+    event ParticipantRemoved(address indexed participant);
+    event WithdrawLockReset();
+    event VrfRefund(uint256 amount);
+    event LotteryReset();
+    event DrawCompleted(uint256 indexed requestId, address winner, uint256 winnerIndex, uint256 randomWord, uint256 prize, uint256 participantCount);
+    event VrfConfigChanged(uint32 callbackGasLimit, uint16 requestConfirmations, uint32 numWords);
     // event ParticipantJoined(address indexed participant, uint256 entryFee, uint256 totalParticipants); // Large Bytecode
 }
 
+// Owner setters accepted by `execute_signed`, encoded exactly like their regular calldata
+#[cfg(feature = "signed-actions")]
+sol! {
+    function setLotteryEntryFee(uint256 fee);
+    function setLotteryIntervalHours(uint256 interval_hours);
+    function setKeeperReward(uint256 reward);
+    function setMaxRequestPrice(uint256 max_price);
+    function setMinParticipants(uint256 min_participants);
+    function setMaxParticipants(uint256 max_participants);
+}
+
 // Define custom errors
 sol! {
     #[derive(Debug)]
     error OnlyVRFWrapperCanFulfill(address have, address want);
+    #[derive(Debug)]
+    error NumWordsAboveMax(uint256 requested, uint256 max);
+    #[derive(Debug)]
+    error ZeroNumWords();
+    #[derive(Debug)]
+    error NotParticipant(address account);
+    #[derive(Debug)]
+    error InvalidBps(uint256 bps);
+    #[derive(Debug)]
+    error TokenNotMintable(address token);
+    #[derive(Debug)]
+    error TokenNotContract(address token);
+    #[derive(Debug)]
+    error RequestNotPending(uint256 request_id);
+    #[derive(Debug)]
+    error DuplicateRequestId(uint256 request_id);
+    #[derive(Debug)]
+    error DrawPending(uint256 request_id);
+    #[derive(Debug)]
+    error ZeroEntryFee();
+    #[derive(Debug)]
+    error IntervalBelowMinimum(uint256 interval_hours, uint256 min_hours);
+    #[derive(Debug)]
+    error InvalidVrfConfig(uint32 callback_gas_limit, uint16 request_confirmations, uint32 num_words);
+    #[derive(Debug)]
+    error InsufficientPoolBalance(uint256 requested, uint256 balance);
+    #[derive(Debug)]
+    error TokenCallFailed(address token);
 }
 
 #[derive(SolidityError, Debug)]
 pub enum Error {
     OnlyVRFWrapperCanFulfill(OnlyVRFWrapperCanFulfill),
+    NumWordsAboveMax(NumWordsAboveMax),
+    ZeroNumWords(ZeroNumWords),
+    NotParticipant(NotParticipant),
+    InvalidBps(InvalidBps),
+    TokenNotMintable(TokenNotMintable),
+    TokenNotContract(TokenNotContract),
+    RequestNotPending(RequestNotPending),
+    DuplicateRequestId(DuplicateRequestId),
+    DrawPending(DrawPending),
+    ZeroEntryFee(ZeroEntryFee),
+    IntervalBelowMinimum(IntervalBelowMinimum),
+    InvalidVrfConfig(InvalidVrfConfig),
+    InsufficientPoolBalance(InsufficientPoolBalance),
+    TokenCallFailed(TokenCallFailed),
     UnauthorizedAccount(ownable::OwnableUnauthorizedAccount),
     InvalidOwner(ownable::OwnableInvalidOwner),
 }
@@ -134,25 +441,35 @@ impl From<ownable::Error> for Error {
 }
 /// Declare that `VrfConsumer` is a contract with the following external methods.
 #[public]
+#[cfg_attr(all(feature = "test-mode", feature = "signed-actions"), implements(ISeededDraw, ISignedActions))]
+#[cfg_attr(all(feature = "test-mode", not(feature = "signed-actions")), implements(ISeededDraw))]
+#[cfg_attr(all(feature = "signed-actions", not(feature = "test-mode")), implements(ISignedActions))]
 impl VrfConsumer {
     /// Constructor - initializes the contract with VRF wrapper address and ERC20 token address
     #[constructor]
     pub fn constructor(
         &mut self,
         vrf_v2_plus_wrapper: Address,
-        owner: Address
+        owner: Address,
+        lottery_name: String
     ) -> Result<(), Error> {        
         self.ownable.constructor(owner)?;
         self.i_vrf_v2_plus_wrapper.set(vrf_v2_plus_wrapper);
+        self.lottery_name.set_str(lottery_name);
         self.erc20_token_address.set(Address::ZERO);
+        self.reward_token_decimals.set(U256::from(NATIVE_DECIMALS));
 
         self.lottery_entry_fee.set(U256::from(500000));
         self.lottery_interval_hours.set(U256::from(4));
-        self.accepting_participants.set(true);
+        self.set_accepting(true);
+        self.round_number.set(U256::from(1));
+        self.genesis_timestamp.set(U256::from(self.vm().block_timestamp()));
         
         self.callback_gas_limit.set(U256::from(100000u32));
         self.request_confirmations.set(U256::from(3u16));
         self.num_words.set(U256::from(1u32));
+        self.max_num_words.set(U256::from(10u32));
+        self.winner_bps.set(U256::from(BPS_DENOMINATOR));
         Ok(())
     }
 
@@ -167,6 +484,9 @@ impl VrfConsumer {
         if self.vm().code_size(external_vrf_wrapper_address) == 0 {
             return Err(b"VRF wrapper contract does not exist at given address".to_vec()); // simple validation but costs 1MiB compiled..
         }
+        if !self.wrapper_supports_native()? {
+            return Err(b"VRF wrapper does not support native payment".to_vec());
+        }
         let external_vrf_wrapper = IVRFV2PlusWrapper::new(external_vrf_wrapper_address);
 
         // Calculate request price
@@ -176,6 +496,17 @@ impl VrfConsumer {
             num_words,
         )?;
 
+        // Persistent ceiling against a mispriced wrapper; zero disables it
+        let max_request_price = self.max_request_price.get();
+        if max_request_price != U256::ZERO && request_price > max_request_price {
+            return Err(b"Price above ceiling".to_vec());
+        }
+        // Fail clearly here rather than with the wrapper's opaque revert
+        if self.vm().balance(self.vm().contract_address()) < request_price {
+            return Err(b"Insufficient contract balance for VRF".to_vec());
+        }
+        self.ensure_native_prize_funded(request_price)?;
+
         let extra_args = get_extra_args_for_native_payment();
 
         // Create call context with value. This is to ensure that the consumer can pay for the request.
@@ -183,6 +514,10 @@ impl VrfConsumer {
         #[allow(deprecated)]
         let config = OldCall::new().value(request_price);
 
+        // The wrapper may refund overpayment, so measure the net spend rather than trusting the quote
+        let contract_address = self.vm().contract_address();
+        let balance_before = self.vm().balance(contract_address);
+
         // Request random words
         let request_id = external_vrf_wrapper.request_random_words_in_native(
             config,
@@ -192,15 +527,52 @@ impl VrfConsumer {
             extra_args,
         )?;
 
-        Ok((request_id, request_price))
+        let actual_paid = balance_before.saturating_sub(self.vm().balance(contract_address));
+
+        // A misbehaving wrapper reusing an id would overwrite that request's records
+        if self.s_requests_paid.get(request_id) != U256::ZERO
+            || self.s_request_timestamp.get(request_id) != U256::ZERO
+        {
+            return Err(Error::DuplicateRequestId(DuplicateRequestId { request_id }).into());
+        }
+
+        Ok((request_id, actual_paid))
+    }
+
+    /// Best-effort probe: a wrapper that is unconfigured, disabled or lacks native pricing
+    /// (e.g. LINK-only) reports false rather than reverting
+    pub fn wrapper_supports_native(&mut self) -> Result<bool, Vec<u8>> {
+        let external_vrf_wrapper = IVRFV2PlusWrapper::new(self.i_vrf_v2_plus_wrapper.get());
+        let configured = external_vrf_wrapper.s_configured(&mut *self).unwrap_or(false);
+        let disabled = external_vrf_wrapper.s_disabled(&mut *self).unwrap_or(true);
+        if !configured || disabled {
+            return Ok(false);
+        }
+        let callback_gas_limit = self.callback_gas_limit.get().try_into().unwrap_or(100000);
+        let num_words = self.num_words.get().try_into().unwrap_or(1);
+        let native_priced = external_vrf_wrapper
+            .calculate_request_price_native(&mut *self, callback_gas_limit, num_words)
+            .is_ok();
+        Ok(native_priced)
+    }
+
+    /// View: whether `request_random_words` would currently pass its preconditions
+    pub fn can_request_draw(&self) -> bool {
+        self.draw_blocked_reason().is_none()
+    }
+
+    /// View: seconds since the last draw was requested, `U256::MAX` if none has been yet
+    pub fn time_since_last_draw(&self) -> U256 {
+        let last = self.last_request_timestamp.get();
+        if last == U256::ZERO {
+            return U256::MAX;
+        }
+        U256::from(self.vm().block_timestamp()).saturating_sub(last)
     }
 
     pub fn request_random_words(&mut self) -> Result<U256, Vec<u8>> {
-        // let interval_secs = self.lottery_interval_hours.get().checked_mul(U256::from(3600)).ok_or_else(|| b"Interval overflow".to_vec())?; // TODO: Below method can overflow, temporarily unhandled for deployment purposes
-        if U256::from(self.vm().block_timestamp())
-        < self.last_request_timestamp.get() + self.lottery_interval_hours.get() * U256::from(3600)
-        {
-            return Err(b"Too soon to resolve lottery".to_vec());
+        if let Some(reason) = self.draw_blocked_reason() {
+            return Err(reason.to_vec());
         }
     
         let callback_gas_limit = self.callback_gas_limit.get().try_into().unwrap_or(100000);
@@ -212,6 +584,12 @@ impl VrfConsumer {
             request_confirmations,
             num_words,
         )?;
+        self.s_requests_paid.setter(request_id).set(req_price);
+        self.s_request_num_words.setter(request_id).set(U32::from(num_words));
+        self.record_request_pool(request_id);
+        let requested_at = U256::from(self.vm().block_timestamp());
+        self.s_request_timestamp.setter(request_id).set(requested_at);
+        self.request_ids.push(request_id);
 
         self.last_request_timestamp.set(U256::from(self.vm().block_timestamp()));
     
@@ -227,22 +605,115 @@ impl VrfConsumer {
         Ok(request_id)
     }
 
+    /// Owner-only: request randomness on behalf of `target`, which receives the words
+    /// through `onRandomWords` instead of them being used for a lottery draw
+    pub fn request_for(&mut self, target: Address) -> Result<U256, Vec<u8>> {
+        self.ownable.only_owner()?;
+        if target == Address::ZERO {
+            return Err(b"Invalid target".to_vec());
+        }
+
+        let callback_gas_limit = self.callback_gas_limit.get().try_into().unwrap_or(100000);
+        let request_confirmations = self.request_confirmations.get().try_into().unwrap_or(3);
+        let num_words = self.num_words.get().try_into().unwrap_or(1);
+
+        let (request_id, req_price) = self.request_randomness_pay_in_native(
+            callback_gas_limit,
+            request_confirmations,
+            num_words,
+        )?;
+        self.s_requests_paid.setter(request_id).set(req_price);
+        self.s_request_num_words.setter(request_id).set(U32::from(num_words));
+        self.s_request_forward_target.setter(request_id).set(target);
+
+        log(
+            self.vm(),
+            RequestSent {
+                requestId: request_id,
+                numWords: num_words,
+                payment: req_price,
+            },
+        );
+
+        Ok(request_id)
+    }
+
+    pub fn get_forward_target(&self, request_id: U256) -> Address {
+        self.s_request_forward_target.get(request_id)
+    }
+
     /// View: get the current native price required to request randomness
-    // pub fn get_request_price(&mut self) -> Result<U256, Vec<u8>> {
-    //     let callback_gas_limit: u32 = self.callback_gas_limit.get().try_into().unwrap_or(100000);
-    //     let num_words: u32 = self.num_words.get().try_into().unwrap_or(1);
+    pub fn get_request_price(&mut self) -> Result<U256, Vec<u8>> {
+        let callback_gas_limit: u32 = self.callback_gas_limit.get().try_into().unwrap_or(100000);
+        let num_words: u32 = self.num_words.get().try_into().unwrap_or(1);
 
-    //     let external_vrf_wrapper_address = self.i_vrf_v2_plus_wrapper.get();
-    //     let external_vrf_wrapper = IVRFV2PlusWrapper::new(external_vrf_wrapper_address);
+        let external_vrf_wrapper_address = self.i_vrf_v2_plus_wrapper.get();
+        let external_vrf_wrapper = IVRFV2PlusWrapper::new(external_vrf_wrapper_address);
 
-    //     let price = external_vrf_wrapper.calculate_request_price_native(
-    //         &mut *self,
-    //         callback_gas_limit,
-    //         num_words,
-    //     )?;
+        let price = external_vrf_wrapper.calculate_request_price_native(
+            &mut *self,
+            callback_gas_limit,
+            num_words,
+        )?;
 
-    //     Ok(price)
-    // }
+        Ok(price)
+    }
+
+    /// View: the exact extraArgs sent with each VRF request (native payment)
+    pub fn get_extra_args(&self) -> stylus_sdk::abi::Bytes {
+        get_extra_args_for_native_payment().to_vec().into()
+    }
+
+    /// View: like `get_request_price` but reports `(false, 0)` instead of reverting when the wrapper fails
+    pub fn try_get_request_price(&mut self) -> (bool, U256) {
+        match self.get_request_price() {
+            Ok(price) => (true, price),
+            Err(_) => (false, U256::ZERO),
+        }
+    }
+
+    /// View: on-chain cost of running a draw (VRF request price plus keeper reward)
+    /// Fulfillment gas is paid by the wrapper's callback budget and isn't included
+    pub fn estimate_draw_cost(&mut self) -> Result<U256, Vec<u8>> {
+        Ok(self.get_request_price()? + self.keeper_reward.get())
+    }
+
+    /// View: rough gas the VRF callback needs for the current round, to size `callback_gas_limit`
+    /// Prizes are pulled with `claim_reward`, so no mint happens inside the callback
+    pub fn estimate_fulfillment_gas(&self) -> U256 {
+        U256::from(FULFILLMENT_BASE_GAS)
+            + U256::from(FULFILLMENT_GAS_PER_PARTICIPANT) * U256::from(self.participants.len())
+    }
+
+    /// View: ETH to top up before the next draw can pay for its VRF request (zero if funded)
+    pub fn shortfall_for_next_draw(&mut self) -> Result<U256, Vec<u8>> {
+        let request_price = self.get_request_price()?;
+        let balance = self.vm().balance(self.vm().contract_address());
+        Ok(request_price.saturating_sub(balance))
+    }
+
+    /// View: (native_balance, reward_token_balance) held by this contract; the token side is zero without a token
+    pub fn total_value_locked(&mut self) -> Result<(U256, U256), Vec<u8>> {
+        let contract_address = self.vm().contract_address();
+        let native_balance = self.vm().balance(contract_address);
+        let token_address = self.erc20_token_address.get();
+        if token_address == Address::ZERO {
+            return Ok((native_balance, U256::ZERO));
+        }
+        let token_balance = IERC20::new(token_address).balance_of(&*self, contract_address)?;
+        Ok((native_balance, token_balance))
+    }
+
+    pub fn keeper_reward(&self) -> U256 {
+        self.keeper_reward.get()
+    }
+
+    /// Per-draw keeper budget counted by `estimate_draw_cost`; the contract doesn't pay it out itself
+    pub fn set_keeper_reward(&mut self, reward: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.keeper_reward.set(reward);
+        Ok(())
+    }
 
     /// Internal function to distribute ERC20 tokens
     fn mint_distribution_reward(
@@ -260,26 +731,147 @@ impl VrfConsumer {
         Ok(())
     }
 
-    /// Internal function to decide the winner
-    fn decide_winner(&mut self, random_words: Vec<U256>) -> Address {
-        if self.participants.is_empty() || random_words.is_empty() {
-            return Address::ZERO;
+    /// Claim the caller's accrued lottery rewards
+    pub fn claim_reward(&mut self) -> Result<(), Vec<u8>> {
+        let caller = self.vm().msg_sender();
+        let amount = self.pending_rewards.get(caller);
+        if amount == U256::ZERO {
+            return Ok(());
         }
+        self.pending_rewards.setter(caller).set(U256::ZERO);
+        self.mint_distribution_reward(caller, amount)
+    }
 
-        let len = self.participants.len() as u64;
-        let idx = (random_words[0] % U256::from(len)).try_into().unwrap_or(0u64) as usize; // Will never overflow in practice but try_into() can hide bugs
-    
-        let winner = self.participants.get(idx).unwrap_or(Address::ZERO);
-    
-        if winner != Address::ZERO {
-            let reward = self.lottery_entry_fee.get() * U256::from(len); 
-            // let reward = self.lottery_entry_fee.get().checked_mul(U256::from(len)).unwrap_or(U256::MAX); // TODO: Above method can overflow, but contract is too big to deploy if I handle it; risk possibility of user getting low rewards for now
-            let _ = self.mint_distribution_reward(winner, reward);
-            while !self.participants.is_empty() {
-                let _ = self.participants.pop();
-            }
+    pub fn pending_reward_of(&self, account: Address) -> U256 {
+        self.pending_rewards.get(account)
+    }
+
+    /// Lifetime prize credited to `who`, claimed or not
+    pub fn total_won_by(&self, who: Address) -> U256 {
+        self.total_won.get(who)
+    }
+
+    pub fn win_count_of(&self, who: Address) -> U256 {
+        self.win_count.get(who)
+    }
+
+    /// Claim a secondary prize of `amount` for `request_id`, proven against the draw's prize Merkle root
+    /// Leaves are keccak256(abi.encodePacked(account, amount)), pairs are hashed in sorted order
+    pub fn claim_merkle_prize(
+        &mut self,
+        request_id: U256,
+        amount: U256,
+        proof: Vec<B256>,
+    ) -> Result<(), Vec<u8>> {
+        let root = self.prize_merkle_root.get(request_id);
+        if root == B256::ZERO {
+            return Err(b"No prize root".to_vec());
         }
-        winner
+        let caller = self.vm().msg_sender();
+        if self.merkle_prize_claimed.getter(request_id).get(caller) {
+            return Err(b"Already claimed".to_vec());
+        }
+        let leaf = self
+            .vm()
+            .native_keccak256(&[caller.as_slice(), &amount.to_be_bytes::<32>()].concat());
+        let computed = proof.iter().fold(leaf, |node, sibling| {
+            let (low, high) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+            self.vm().native_keccak256(&[low.0, high.0].concat())
+        });
+        if computed != root {
+            return Err(b"Invalid proof".to_vec());
+        }
+        self.merkle_prize_claimed.setter(request_id).setter(caller).set(true);
+        self.mint_distribution_reward(caller, amount)
+    }
+
+    pub fn prize_merkle_root(&self, request_id: U256) -> B256 {
+        self.prize_merkle_root.get(request_id)
+    }
+
+    pub fn merkle_prize_claimed(&self, request_id: U256, account: Address) -> bool {
+        self.merkle_prize_claimed.getter(request_id).get(account)
+    }
+
+    /// Owner-only: commit the secondary prizes of a draw
+    pub fn set_prize_merkle_root(&mut self, request_id: U256, root: B256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.prize_merkle_root.setter(request_id).set(root);
+        Ok(())
+    }
+
+    /// Claim the vested, unclaimed part of the caller's prize stream
+    pub fn claim_streamed_reward(&mut self) -> Result<(), Vec<u8>> {
+        let caller = self.vm().msg_sender();
+        let amount = self.streamed_reward_claimable(caller);
+        if amount == U256::ZERO {
+            return Ok(());
+        }
+        let claimed = self.stream_claimed.get(caller);
+        self.stream_claimed.setter(caller).set(claimed + amount);
+        self.mint_distribution_reward(caller, amount)
+    }
+
+    pub fn streamed_reward_claimable(&self, account: Address) -> U256 {
+        self.vested_stream(account) - self.stream_claimed.get(account)
+    }
+
+    /// Returns (total, claimed, start, duration) of `account`'s prize stream
+    pub fn get_reward_stream(&self, account: Address) -> (U256, U256, U256, U256) {
+        (
+            self.stream_total.get(account),
+            self.stream_claimed.get(account),
+            self.stream_start.get(account),
+            self.stream_duration.get(account),
+        )
+    }
+
+    /// Pull queued ETH (unpushed refunds, native prizes, the treasury's auto-withdraw cut); a no-op when nothing is owed
+    pub fn claim_refund(&mut self) -> Result<(), Vec<u8>> {
+        if self.flag(FLAG_CLAIMING_REFUND) {
+            return Err(b"Refund claim in progress".to_vec());
+        }
+        let caller = self.vm().msg_sender();
+        let amount = self.pending_refunds.get(caller);
+        if amount == U256::ZERO {
+            return Ok(());
+        }
+        self.set_flag(FLAG_CLAIMING_REFUND, true);
+        self.pending_refunds.setter(caller).set(U256::ZERO);
+        self.total_pending_refunds.set(self.total_pending_refunds.get() - amount);
+        let result = self.vm().call(&Call::new().value(amount), caller, &[]);
+        self.set_flag(FLAG_CLAIMING_REFUND, false);
+        result?;
+        Ok(())
+    }
+
+    pub fn pending_refund_of(&self, who: Address) -> U256 {
+        self.pending_refunds.get(who)
+    }
+
+    /// Pull a token refund that couldn't be pushed; a no-op when nothing is owed
+    pub fn claim_token_refund(&mut self, token: Address) -> Result<(), Vec<u8>> {
+        let caller = self.vm().msg_sender();
+        let amount = self.pending_token_refunds.getter(token).get(caller);
+        if amount == U256::ZERO {
+            return Ok(());
+        }
+        self.pending_token_refunds.setter(token).setter(caller).set(U256::ZERO);
+        let total = self.total_pending_token_refunds.get(token);
+        self.total_pending_token_refunds.setter(token).set(total - amount);
+        if !IERC20::new(token).transfer(&mut *self, caller, amount)? {
+            return Err(b"Token transfer failed".to_vec());
+        }
+        Ok(())
+    }
+
+    pub fn pending_token_refund_of(&self, token: Address, who: Address) -> U256 {
+        self.pending_token_refunds.getter(token).get(who)
+    }
+
+    /// Returns (asset, amount) `account` paid into the current round; the asset is zero for native entries
+    pub fn get_entry_payment(&self, account: Address) -> (Address, U256) {
+        (self.entry_asset.get(account), self.entry_paid.get(account))
     }
 
     // pub fn raw_fulfill_random_words(
@@ -306,7 +898,7 @@ impl VrfConsumer {
         
     //     self.last_fulfilled_id.set(request_id);
     //     self.last_fulfilled_value.set(fulfilled_value);    
-    //     self.accepting_participants.set(false);
+    //     self.set_accepting(false);
     
     //     let winner_address = self.decide_winner(random_words.clone());
     
@@ -318,7 +910,7 @@ impl VrfConsumer {
     //             winner: winner_address,
     //         },
     //     );
-    //     self.accepting_participants.set(true); // accept new participants again
+    //     self.set_accepting(true); // accept new participants again
     //     Ok(())
     // }
 
@@ -328,6 +920,15 @@ impl VrfConsumer {
         request_id: U256,
         random_words: Vec<U256>,
     ) -> Result<(), Error> {
+        // Forwarded requests belong to a sub-consumer and don't touch lottery state
+        let forward_target = self.s_request_forward_target.get(request_id);
+        if forward_target != Address::ZERO {
+            self.s_request_forward_target.setter(request_id).set(Address::ZERO);
+            let receiver = IRandomWordsReceiver::new(forward_target);
+            let _ = receiver.on_random_words(&mut *self, request_id, random_words); // a failing target must not revert the callback
+            return Ok(());
+        }
+
         // Store only the last fulfilled request
         let fulfilled_value = if !random_words.is_empty() {
             random_words[0]
@@ -337,20 +938,67 @@ impl VrfConsumer {
         
         self.last_fulfilled_id.set(request_id);
         self.last_fulfilled_value.set(fulfilled_value);    
-        self.accepting_participants.set(false);
-    
-        let winner_address = self.decide_winner(random_words.clone());
-        // self.last_winner.set(winner_address);
-    
-        log(
-            self.vm(), // emit the event in the current contract's execution context
-            RequestFulfilled {
-                requestId: request_id,
-                randomWords: random_words.clone(),
-                winner: winner_address,
-            },
-        );
-        self.accepting_participants.set(true); // accept new participants again
+        self.set_accepting(false);
+
+        // Track VRF latency for monitoring
+        let fulfilled_at = U256::from(self.vm().block_timestamp());
+        let first_fulfillment = self.s_fulfilled_timestamp.get(request_id) == U256::ZERO;
+        self.s_fulfilled_timestamp.setter(request_id).set(fulfilled_at);
+        let requested_at = self.s_request_timestamp.get(request_id);
+        if first_fulfillment && requested_at != U256::ZERO {
+            self.fulfilled_count.set(self.fulfilled_count.get() + U256::from(1));
+            self.total_fulfillment_latency
+                .set(self.total_fulfillment_latency.get() + (fulfilled_at - requested_at));
+        }
+
+        // Mixed here rather than at settlement, so whoever calls `finalize_draw` can't pick the block
+        let mut draw_word = random_words.first().copied();
+        if self.flag(FLAG_MIX_BLOCK_DATA) {
+            draw_word = draw_word.map(|word| self.mixed_with_block_data(word));
+        }
+
+        // Winner selection walks the participant list; past the threshold it could run the callback out of gas
+        let max_participants = self.max_fulfillment_participants.get();
+        if max_participants != U256::ZERO && U256::from(self.participants.len()) > max_participants {
+            self.s_draw_random_word.setter(request_id).set(draw_word.unwrap_or(U256::ZERO));
+            self.deferred_draw_id.set(request_id);
+            self.set_flag(FLAG_DRAW_DEFERRED, true);
+            return Ok(());
+        }
+
+        self.settle_draw(request_id, draw_word, random_words);
+        Ok(())
+    }
+
+    /// Pick the winner of a draw whose fulfillment was deferred, from the word stored by the callback
+    /// Entries stay closed until then
+    pub fn finalize_draw(&mut self) -> Result<Address, Vec<u8>> {
+        if !self.flag(FLAG_DRAW_DEFERRED) {
+            return Err(b"No deferred draw".to_vec());
+        }
+        self.set_flag(FLAG_DRAW_DEFERRED, false);
+        let request_id = self.deferred_draw_id.get();
+        let random_word = self.s_draw_random_word.get(request_id);
+        Ok(self.settle_draw(request_id, Some(random_word), vec![random_word]))
+    }
+
+    pub fn draw_deferred(&self) -> bool {
+        self.flag(FLAG_DRAW_DEFERRED)
+    }
+
+    /// The draw awaiting `finalize_draw`; only meaningful while `draw_deferred` is true
+    pub fn deferred_draw_id(&self) -> U256 {
+        self.deferred_draw_id.get()
+    }
+
+    pub fn max_fulfillment_participants(&self) -> U256 {
+        self.max_fulfillment_participants.get()
+    }
+
+    /// Zero always picks the winner inside the callback
+    pub fn set_max_fulfillment_participants(&mut self, max_participants: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.max_fulfillment_participants.set(max_participants);
         Ok(())
     }
 
@@ -360,7 +1008,12 @@ impl VrfConsumer {
         request_id: U256,
         random_words: Vec<U256>,
     ) -> Result<(), Error> {
-        let vrf_wrapper_addr = self.i_vrf_v2_plus_wrapper.get();
+        let migrated_wrapper = self.s_request_wrapper.get(request_id);
+        let vrf_wrapper_addr = if migrated_wrapper != Address::ZERO {
+            migrated_wrapper
+        } else {
+            self.i_vrf_v2_plus_wrapper.get()
+        };
         let msg_sender = self.vm().msg_sender();
         if msg_sender != vrf_wrapper_addr {
             return Err(Error::OnlyVRFWrapperCanFulfill(OnlyVRFWrapperCanFulfill {
@@ -368,59 +1021,372 @@ impl VrfConsumer {
                 want: vrf_wrapper_addr,
             }));
         }
+        if migrated_wrapper != Address::ZERO {
+            self.s_request_wrapper.setter(request_id).set(Address::ZERO);
+        }
 
         self.fulfill_random_words(request_id, random_words)
     }
     
-    pub fn get_last_fulfilled_id(&self) -> U256 {
-        self.last_fulfilled_id.get()
+    /// Net native amount spent on a request, after any wrapper refund
+    pub fn get_actual_paid(&self, request_id: U256) -> U256 {
+        self.s_requests_paid.get(request_id)
     }
 
-    pub fn get_last_fulfilled_value(&self) -> U256 {
-        self.last_fulfilled_value.get()
+    /// Words requested for `request_id`, which may differ from the current `num_words`
+    pub fn get_request_num_words(&self, request_id: U256) -> Result<u32, Vec<u8>> {
+        let num_words = self.s_request_num_words.get(request_id);
+        if num_words == U32::ZERO {
+            return Err(b"Request not found".to_vec());
+        }
+        Ok(num_words.to::<u32>())
     }
 
-    // pub fn get_last_winner(&self) -> Address {
-    //     self.last_winner.get()
-    // }
+    /// Prize pool (what entrants had paid in) when the draw was requested
+    pub fn get_request_pool(&self, request_id: U256) -> Result<U256, Vec<u8>> {
+        if self.s_request_timestamp.get(request_id) == U256::ZERO {
+            return Err(b"Request not found".to_vec());
+        }
+        Ok(self.s_request_pool.get(request_id))
+    }
 
-    // pub fn destroy(&self) -> Result<(), Error> {
-    //     // pass
-    // }
+    /// View: (winner_index, announced_winner) for a fulfilled draw
+    /// The index is rebuilt from the position the word landed on (after tickets and the early-entry
+    /// bonus) and the eligibility re-rolls, exactly as the draw applied them
+    pub fn verify_draw(&self, request_id: U256) -> Result<(U256, Address), Vec<u8>> {
+        if self.s_draw_ticket_count.get(request_id) == U256::ZERO {
+            return Err(b"Unknown draw".to_vec());
+        }
+        let index = rerolled_index(
+            self.s_draw_drawn_index.get(request_id).saturating_to(),
+            self.s_draw_rerolls.get(request_id).saturating_to(),
+            self.s_draw_participant_count.get(request_id).saturating_to(),
+        );
+        Ok((U256::from(index), self.s_draw_winner.get(request_id)))
+    }
 
-    /// Allows the owner to retrieve balances
-    // pub fn withdraw_native(&mut self, amount: U256) -> Result<(), Vec<u8>> {
-    //     self.ownable.only_owner()?;    
-    //     if self.withdrawing.get() {
-    //         return Err(b"Withdrawal in progress".to_vec());
-    //     }
-    //     self.withdrawing.set(true);
-    //     self.vm().call(&Call::new().value(amount), self.ownable.owner(), &[])?;
-    //     self.withdrawing.set(false);
-    //     Ok(())
-    // }
+    /// View: 0-based index in the participant list of the draw's winner
+    pub fn get_winner_index(&self, request_id: U256) -> Result<U256, Vec<u8>> {
+        if self.s_draw_ticket_count.get(request_id) == U256::ZERO {
+            return Err(b"Unknown draw".to_vec());
+        }
+        Ok(self.s_winner_index.get(request_id))
+    }
 
-    pub fn i_vrf_v2_plus_wrapper(&self) -> Address {
-        self.i_vrf_v2_plus_wrapper.get()
+    /// View: whether `account` is the recorded winner of a fulfilled draw
+    pub fn did_win(&self, request_id: U256, account: Address) -> Result<bool, Vec<u8>> {
+        if self.s_draw_ticket_count.get(request_id) == U256::ZERO {
+            return Err(b"Unknown draw".to_vec());
+        }
+        Ok(account != Address::ZERO && self.s_draw_winner.get(request_id) == account)
     }
 
-    pub fn erc20_token_address(&self) -> Address {
-        self.erc20_token_address.get()
+    /// Number of draws requested so far, including pruned ones
+    pub fn get_draw_count(&self) -> U256 {
+        U256::from(self.request_ids.len())
     }
 
-    pub fn set_erc20_token(&mut self, token_address: Address) -> Result<(), Error> {
-        self.ownable.only_owner()?;
-        self.erc20_token_address.set(token_address);
-        Ok(())
+    /// `(total_requests, fulfilled_count)` in one call; both survive pruning
+    pub fn request_counts(&self) -> (U256, U256) {
+        (U256::from(self.request_ids.len()), self.fulfilled_count.get())
+    }
+
+    pub fn get_max_retained_draws(&self) -> U256 {
+        self.max_retained_draws.get()
+    }
+
+    /// Owner-only: clear per-request data for fulfilled draws beyond the newest `max_retained_draws`
+    /// Pruning stops at the first pending or deferred draw so its data is never lost; returns how many were pruned
+    pub fn prune_old_draws(&mut self) -> Result<U256, Vec<u8>> {
+        self.ownable.only_owner()?;
+
+        let total = self.request_ids.len();
+        let retained: usize = self.max_retained_draws.get().try_into().unwrap_or(usize::MAX);
+        let mut cursor: usize = self.pruned_draws.get().try_into().unwrap_or(total);
+        let mut pruned = 0u64;
+        while cursor < total && total - cursor > retained {
+            let request_id = self.request_ids.get(cursor).unwrap_or(U256::ZERO);
+            // A deferred draw is fulfilled but still needs its stored word for `finalize_draw`
+            let deferred = self.flag(FLAG_DRAW_DEFERRED) && self.deferred_draw_id.get() == request_id;
+            if deferred || self.s_fulfilled_timestamp.get(request_id) == U256::ZERO {
+                break;
+            }
+            self.s_requests_paid.setter(request_id).set(U256::ZERO);
+            self.s_request_pool.setter(request_id).set(U256::ZERO);
+            self.s_request_num_words.setter(request_id).set(U32::ZERO);
+            self.s_request_timestamp.setter(request_id).set(U256::ZERO);
+            self.s_fulfilled_timestamp.setter(request_id).set(U256::ZERO);
+            self.s_draw_random_word.setter(request_id).set(U256::ZERO);
+            self.s_draw_ticket_count.setter(request_id).set(U256::ZERO);
+            self.s_draw_winner.setter(request_id).set(Address::ZERO);
+            self.s_winner_index.setter(request_id).set(U256::ZERO);
+            self.s_draw_drawn_index.setter(request_id).set(U256::ZERO);
+            self.s_draw_rerolls.setter(request_id).set(U256::ZERO);
+            self.s_draw_participant_count.setter(request_id).set(U256::ZERO);
+            cursor += 1;
+            pruned += 1;
+        }
+        self.pruned_draws.set(U256::from(cursor));
+        Ok(U256::from(pruned))
+    }
+
+    /// View: most recent winners, oldest first (bounded by RECENT_WINNERS_CAPACITY)
+    pub fn get_recent_winners(&self) -> Vec<Address> {
+        let len = self.recent_winners.len();
+        let head: usize = self.recent_winners_head.get().try_into().unwrap_or(0);
+        (0..len)
+            .filter_map(|i| self.recent_winners.get((head + i) % len))
+            .collect()
+    }
+
+    /// Seconds between a request being sent and fulfilled
+    pub fn get_fulfillment_latency(&self, request_id: U256) -> Result<U256, Vec<u8>> {
+        let requested_at = self.s_request_timestamp.get(request_id);
+        if requested_at == U256::ZERO {
+            return Err(b"Request not found".to_vec());
+        }
+        let fulfilled_at = self.s_fulfilled_timestamp.get(request_id);
+        if fulfilled_at == U256::ZERO {
+            return Err(b"Request not fulfilled".to_vec());
+        }
+        Ok(fulfilled_at - requested_at)
+    }
+
+    /// View: average fulfillment latency in seconds across fulfilled requests (zero if none)
+    pub fn get_average_fulfillment_latency(&self) -> U256 {
+        let count = self.fulfilled_count.get();
+        if count == U256::ZERO {
+            return U256::ZERO;
+        }
+        self.total_fulfillment_latency.get() / count
+    }
+
+    pub fn get_last_fulfilled_id(&self) -> U256 {
+        self.last_fulfilled_id.get()
+    }
+
+    pub fn get_last_fulfilled_value(&self) -> U256 {
+        self.last_fulfilled_value.get()
+    }
+
+    // pub fn get_last_winner(&self) -> Address {
+    //     self.last_winner.get()
+    // }
+
+    // pub fn destroy(&self) -> Result<(), Error> {
+    //     // pass
+    // }
+
+    /// Allows the owner to retrieve balances
+    // pub fn withdraw_native(&mut self, amount: U256) -> Result<(), Vec<u8>> {
+    //     self.ownable.only_owner()?;    
+    //     if self.is_withdrawing() {
+    //         return Err(b"Withdrawal in progress".to_vec());
+    //     }
+    //     self.set_withdrawing(true);
+    //     self.vm().call(&Call::new().value(amount), self.ownable.owner(), &[])?;
+    //     self.set_withdrawing(false);
+    //     Ok(())
+    // }
+
+    /// Owner-only: send the native balance above the next draw's cost (VRF price plus keeper reward), the
+    /// ETH paid into the live round and the queued refunds to `to`. Returns the swept amount
+    pub fn sweep_dust(&mut self, to: Address) -> Result<U256, Vec<u8>> {
+        self.ownable.only_owner()?;
+        if self.is_withdrawing() {
+            return Err(b"Withdrawal in progress".to_vec());
+        }
+        let reserved = self.estimate_draw_cost()?.max(self.min_operational_balance.get())
+            + self.current_native_pool.get()
+            + self.total_pending_refunds.get();
+        let balance = self.vm().balance(self.vm().contract_address());
+        let amount = balance.saturating_sub(reserved);
+        if amount == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+        self.set_withdrawing(true);
+        let result = self.vm().call(&Call::new().value(amount), to, &[]);
+        self.set_withdrawing(false);
+        result?;
+        Ok(amount)
+    }
+
+    /// Owner-only: withdraw `amounts[i]` of `tokens[i]` to the owner, `Address::ZERO` meaning native ETH
+    pub fn withdraw_batch(&mut self, tokens: Vec<Address>, amounts: Vec<U256>) -> Result<(), Vec<u8>> {
+        self.ownable.only_owner()?;
+        if tokens.len() != amounts.len() {
+            return Err(b"Length mismatch".to_vec());
+        }
+        if self.is_withdrawing() {
+            return Err(b"Withdrawal in progress".to_vec());
+        }
+        self.set_withdrawing(true);
+        let result = self.withdraw_each(&tokens, &amounts);
+        self.set_withdrawing(false);
+        result
+    }
+
+    /// Owner-only: start a new season with no participants, entries open and no draw on record
+    /// Each entrant's payment is queued for `claim_refund` / `claim_token_refund`; draw history stays until `prune_old_draws`
+    pub fn reset_lottery(&mut self) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        if self.flag(FLAG_DRAW_DEFERRED) {
+            let request_id = self.deferred_draw_id.get();
+            return Err(Error::DrawPending(DrawPending { request_id }));
+        }
+        if let Some(request_id) = self.request_ids.get(self.request_ids.len().wrapping_sub(1)) {
+            if self.s_fulfilled_timestamp.get(request_id) == U256::ZERO
+                && self.s_request_timestamp.get(request_id) != U256::ZERO
+            {
+                return Err(Error::DrawPending(DrawPending { request_id }));
+            }
+        }
+        for i in 0..self.participants.len() {
+            let participant = self.participants.get(i).unwrap_or(Address::ZERO);
+            let asset = self.entry_asset.get(participant);
+            let paid = self.entry_paid.get(participant);
+            self.queue_entry_refund(participant, asset, paid);
+        }
+        self.clear_participants();
+        self.set_accepting(true);
+        self.last_request_timestamp.set(U256::ZERO);
+        log(self.vm(), LotteryReset {});
+        Ok(())
+    }
+
+    /// Owner-only recovery: clear a withdraw lock left stuck on by a failed withdrawal
+    pub fn force_reset_withdraw_lock(&mut self) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_withdrawing(false);
+        log(self.vm(), WithdrawLockReset {});
+        Ok(())
+    }
+
+    pub fn i_vrf_v2_plus_wrapper(&self) -> Address {
+        self.i_vrf_v2_plus_wrapper.get()
+    }
+
+    /// Owner-only: point at a new VRF wrapper; in-flight requests need `migrate_pending_request`
+    pub fn set_vrf_wrapper(&mut self, vrf_v2_plus_wrapper: Address) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.i_vrf_v2_plus_wrapper.set(vrf_v2_plus_wrapper);
+        Ok(())
+    }
+
+    /// Owner-only: let `old_wrapper` fulfill a still-pending `request_id` after a wrapper change
+    pub fn migrate_pending_request(
+        &mut self,
+        request_id: U256,
+        old_wrapper: Address,
+    ) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        let pending_draw = self.s_request_timestamp.get(request_id) != U256::ZERO
+            && self.s_fulfilled_timestamp.get(request_id) == U256::ZERO;
+        let pending_forward = self.s_request_forward_target.get(request_id) != Address::ZERO;
+        if !pending_draw && !pending_forward {
+            return Err(Error::RequestNotPending(RequestNotPending { request_id }));
+        }
+        self.s_request_wrapper.setter(request_id).set(old_wrapper);
+        Ok(())
+    }
+
+    pub fn get_request_wrapper(&self, request_id: U256) -> Address {
+        self.s_request_wrapper.get(request_id)
+    }
+
+    pub fn erc20_token_address(&self) -> Address {
+        self.erc20_token_address.get()
+    }
+
+    /// `Address::ZERO` clears the token; anything else must have code
+    pub fn set_erc20_token(&mut self, token_address: Address) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        if token_address != Address::ZERO && self.vm().code_size(token_address) == 0 {
+            return Err(Error::TokenNotContract(TokenNotContract { token: token_address }));
+        }
+        let mintable = self.can_mint_token(token_address);
+        if self.flag(FLAG_REQUIRE_MINTABLE_TOKEN) && token_address != Address::ZERO && !mintable {
+            return Err(Error::TokenNotMintable(TokenNotMintable { token: token_address }));
+        }
+        let decimals = self.query_token_decimals(token_address);
+        self.erc20_token_address.set(token_address);
+        self.reward_token_decimals.set(U256::from(decimals));
+        self.set_flag(FLAG_REWARD_TOKEN_MINTABLE, mintable);
+        Ok(())
+    }
+
+    pub fn reward_token_decimals(&self) -> U256 {
+        self.reward_token_decimals.get()
+    }
+
+    pub fn reward_token_is_mintable(&self) -> bool {
+        self.flag(FLAG_REWARD_TOKEN_MINTABLE)
+    }
+
+    /// View: (token_set, mintable), re-probing MINTER_ROLE so a revoked role shows up before a failed claim
+    pub fn reward_status(&mut self) -> (bool, bool) {
+        let token_address = self.erc20_token_address.get();
+        (token_address != Address::ZERO, self.can_mint_token(token_address))
+    }
+
+    pub fn require_mintable_token(&self) -> bool {
+        self.flag(FLAG_REQUIRE_MINTABLE_TOKEN)
+    }
+
+    /// When set, `set_erc20_token` rejects tokens that haven't granted this contract MINTER_ROLE
+    pub fn set_require_mintable_token(&mut self, required: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_REQUIRE_MINTABLE_TOKEN, required);
+        Ok(())
+    }
+
+    pub fn auto_restart(&self) -> bool {
+        !self.flag(FLAG_MANUAL_RESTART)
+    }
+
+    /// When disabled, entries stay closed after a draw until the owner calls `start_new_round`
+    pub fn set_auto_restart(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_MANUAL_RESTART, !enabled);
+        Ok(())
+    }
+
+    /// Owner-only: reopen entries after a draw when auto-restart is off
+    pub fn start_new_round(&mut self) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_accepting(true);
+        Ok(())
     }
 
     pub fn accepting_participants(&self) -> bool {
-        self.accepting_participants.get()
+        self.is_accepting()
+    }
+
+    pub fn participation_paused(&self) -> bool {
+        self.flag(FLAG_PARTICIPATION_PAUSED)
+    }
+
+    /// Freeze new entries and ticket top-ups; draws of the current round still go ahead
+    pub fn set_participation_paused(&mut self, paused: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_PARTICIPATION_PAUSED, paused);
+        Ok(())
+    }
+
+    pub fn draws_paused(&self) -> bool {
+        self.flag(FLAG_DRAWS_PAUSED)
+    }
+
+    /// Block `request_random_words` without touching participation
+    pub fn set_draws_paused(&mut self, paused: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_DRAWS_PAUSED, paused);
+        Ok(())
     }
 
     // /// Set the event started flag (internal)
     // fn set_accepting_participants(&mut self, started: bool) -> Result<(), Error> {
-    //     self.accepting_participants.set(started);
+    //     self.set_accepting(started);
     //     Ok(())
     // }
 
@@ -438,22 +1404,43 @@ impl VrfConsumer {
     //         .ok_or_else(|| b"OOB".to_vec())
     // }
 
+    /// View: why participation is closed, as a status code
+    /// 0 = open, 1 = paused, 2 = draw in progress, 3 = retired, 4 = window closed, 5 = full
+    pub fn participation_status(&self) -> u8 {
+        if self.flag(FLAG_DRAW_DEFERRED) {
+            return STATUS_DRAW_IN_PROGRESS;
+        }
+        if !self.is_accepting() || self.flag(FLAG_PARTICIPATION_PAUSED) {
+            return STATUS_PAUSED;
+        }
+        if self.is_full() {
+            return STATUS_FULL;
+        }
+        STATUS_OPEN
+    }
+
+    /// View: (can_participate, entry fee to send, reason code) for `account` entering with `participate_in_lottery`
+    /// Reason codes follow `participation_status`, plus 6 = already participating, 7 = insufficient token
+    /// balance, 8 = contracts not allowed, 9 = removed this round, 10 = cooldown active, 11 = entered this block
+    pub fn participation_quote(&self, account: Address) -> (bool, U256, u8) {
+        let fee = self.lottery_entry_fee.get();
+        let reason = self
+            .entry_rejection(account)
+            .or_else(|| self.rate_limit_rejection(account))
+            .map_or(STATUS_OPEN, |(code, _)| code);
+        (reason == STATUS_OPEN, fee, reason)
+    }
+
     /// Participate in the lottery by paying the entry fee
     /// Takes a flat amount from user's wallet and adds them to participants list
     #[payable]
     pub fn participate_in_lottery(&mut self) -> Result<(), Vec<u8>> {
-        if !self.accepting_participants.get() {
-            return Err(b"Not accepting participants".to_vec());
-        }
-
         let msg_sender = self.vm().msg_sender();
-        for i in 0..self.participants.len() {
-            if self.participants.get(i) == Some(msg_sender) {
-                return Err(b"Already participating".to_vec());
-            }
-        }
+        self.ensure_can_enter(msg_sender)?;
+        self.note_entry_block(msg_sender)?;
+
         let entry_fee = self.lottery_entry_fee.get();        
-        if entry_fee == U256::ZERO {
+        if entry_fee == U256::ZERO && !self.flag(FLAG_FREE_ENTRY) {
             return Err(b"Fee not set".to_vec());
         }
 
@@ -461,7 +1448,9 @@ impl VrfConsumer {
         if sent_amount != entry_fee {
             return Err(b"Wrong amount".to_vec());
         }
-        self.participants.push(self.vm().msg_sender());
+        self.ensure_within_balance_cap()?;
+        self.record_entry(msg_sender);
+        self.note_payment(msg_sender, Address::ZERO, entry_fee);
         
         // log(
         //     self.vm(),
@@ -475,50 +1464,1724 @@ impl VrfConsumer {
         Ok(())
     }
 
-    pub fn lottery_entry_fee(&self) -> U256 { // In Wei (Eth)
-        self.lottery_entry_fee.get()
-    }
+    /// Enter through a fee tier; the tier's weight becomes the caller's ticket count
+    #[payable]
+    pub fn participate_in_tier(&mut self, tier: u8) -> Result<(), Vec<u8>> {
+        let tier = U8::from(tier);
+        let fee = self.tier_fee.get(tier);
+        let weight = self.tier_weight.get(tier);
+        if fee == U256::ZERO || weight == U256::ZERO {
+            return Err(b"Tier not configured".to_vec());
+        }
+        if self.vm().msg_value() != fee {
+            return Err(b"Wrong amount".to_vec());
+        }
+        self.ensure_within_balance_cap()?;
 
-    pub fn set_lottery_entry_fee(&mut self, fee: U256) -> Result<(), Error> {// In Wei (Eth)
-        self.ownable.only_owner()?;
-        self.lottery_entry_fee.set(fee);
+        let msg_sender = self.vm().msg_sender();
+        self.ensure_can_enter(msg_sender)?;
+        self.note_entry_block(msg_sender)?;
+        self.record_entry_with_tickets(msg_sender, weight);
+        self.note_payment(msg_sender, Address::ZERO, fee);
         Ok(())
     }
 
-    pub fn lottery_interval_hours(&self) -> U256 {
-        self.lottery_interval_hours.get()
+    /// Returns (fee, weight) for `tier`
+    pub fn get_tier(&self, tier: u8) -> (U256, U256) {
+        let tier = U8::from(tier);
+        (self.tier_fee.get(tier), self.tier_weight.get(tier))
     }
 
-    pub fn set_lottery_interval_hours(&mut self, interval_hours: U256) -> Result<(), Error> {
+    /// Owner-only: configure a tier; a zero fee or weight disables it
+    pub fn set_tier(&mut self, tier: u8, fee: U256, weight: U256) -> Result<(), Error> {
         self.ownable.only_owner()?;
-        self.lottery_interval_hours.set(interval_hours);
+        let tier = U8::from(tier);
+        self.tier_fee.setter(tier).set(fee);
+        self.tier_weight.setter(tier).set(weight);
         Ok(())
     }
 
-    /// Receive function equivalent - handles incoming ETH
-    #[receive]
+    /// Buy `count` tickets at the entry fee each; more tickets means a proportionally higher chance
+    /// New participants are registered, existing ones top up their ticket count
     #[payable]
-    pub fn receive(&mut self) -> Result<(), Vec<u8>> {
-        log(
-            self.vm(),
-            Received {
-                sender: self.vm().msg_sender(),
-                value: self.vm().msg_value(),
-            },
+    pub fn buy_tickets(&mut self, count: U256) -> Result<(), Vec<u8>> {
+        if count == U256::ZERO {
+            return Err(b"Zero tickets".to_vec());
+        }
+        let entry_fee = self.lottery_entry_fee.get();
+        if entry_fee == U256::ZERO && !self.flag(FLAG_FREE_ENTRY) {
+            return Err(b"Fee not set".to_vec());
+        }
+        if self.vm().msg_value() != entry_fee * count {
+            return Err(b"Wrong amount".to_vec());
+        }
+        self.ensure_within_balance_cap()?;
+
+        let msg_sender = self.vm().msg_sender();
+        let position = self.participant_position.get(msg_sender);
+        if position == U256::ZERO {
+            self.ensure_can_enter(msg_sender)?;
+            self.note_entry_block(msg_sender)?;
+            self.record_entry_with_tickets(msg_sender, count);
+        } else {
+            // Top-ups are paid in ETH, so they can't be mixed with a token entry's refund
+            if self.entry_asset.get(msg_sender) != Address::ZERO {
+                return Err(b"Entered with token".to_vec());
+            }
+            if !self.is_accepting() {
+                return Err(b"Not accepting participants".to_vec());
+            }
+            if self.flag(FLAG_PARTICIPATION_PAUSED) {
+                return Err(b"Participation paused".to_vec());
+            }
+            self.note_entry_block(msg_sender)?;
+            self.add_tickets(msg_sender, position.try_into().unwrap_or(0), count);
+        }
+        self.note_payment(msg_sender, Address::ZERO, entry_fee * count);
+        Ok(())
+    }
+
+    pub fn get_ticket_count(&self, account: Address) -> U256 {
+        self.ticket_count.get(account)
+    }
+
+    pub fn get_total_tickets(&self) -> U256 {
+        self.total_tickets.get()
+    }
+
+    /// View: participants alongside their ticket counts as parallel arrays
+    pub fn get_participants_with_tickets(&self) -> (Vec<Address>, Vec<U256>) {
+        self.participants_with_tickets_page(0, self.participants.len())
+    }
+
+    /// Paginated `get_participants_with_tickets` for large lotteries; out-of-range pages are empty
+    pub fn get_participants_with_tickets_page(
+        &self,
+        offset: U256,
+        limit: U256,
+    ) -> (Vec<Address>, Vec<U256>) {
+        let offset: usize = offset.try_into().unwrap_or(usize::MAX);
+        let limit: usize = limit.try_into().unwrap_or(usize::MAX);
+        self.participants_with_tickets_page(offset, limit)
+    }
+
+    /// View: total tickets held by the first `count` participants
+    pub fn get_cumulative_tickets(&self, count: U256) -> U256 {
+        let position: usize = count.try_into().unwrap_or(usize::MAX).min(self.participants.len());
+        self.fenwick_prefix_sum(position)
+    }
+
+    /// Token-mode entry: called by the configured reward token when a holder transfers
+    /// exactly the entry fee to this contract, registering that holder as a participant
+    pub fn register_token_entry(&mut self, participant: Address) -> Result<(), Vec<u8>> {
+        let token_address = self.erc20_token_address.get();
+        if token_address == Address::ZERO || self.vm().msg_sender() != token_address {
+            return Err(b"Only reward token".to_vec());
+        }
+        self.ensure_can_enter(participant)?;
+        self.note_entry_block(participant)?;
+        self.record_entry(participant);
+        let entry_fee = self.lottery_entry_fee.get();
+        self.note_payment(participant, token_address, entry_fee);
+        Ok(())
+    }
+
+    /// Token-mode entry: pulls the entry fee in reward tokens from the caller (requires prior approval)
+    /// `entry_burn_bps` of the fee is burned immediately, the remainder stays in the pool
+    pub fn participate_with_token(&mut self) -> Result<(), Vec<u8>> {
+        let msg_sender = self.vm().msg_sender();
+        self.enter_with_token(msg_sender)
+    }
+
+    /// Single-transaction token entry: sets the allowance with an EIP-2612 permit, then pulls the fee
+    /// A failing permit is ignored so a front-run permit doesn't block entry; the pull still needs allowance
+    pub fn permit_and_participate(
+        &mut self,
+        value: U256,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<(), Vec<u8>> {
+        let token_address = self.erc20_token_address.get();
+        if token_address == Address::ZERO {
+            return Err(b"Token not set".to_vec());
+        }
+        let msg_sender = self.vm().msg_sender();
+        let contract_address = self.vm().contract_address();
+        let _ = IERC20Permit::new(token_address).permit(
+            &mut *self,
+            msg_sender,
+            contract_address,
+            value,
+            deadline,
+            v,
+            r,
+            s,
         );
+        self.enter_with_token(msg_sender)
+    }
+
+    pub fn entry_burn_bps(&self) -> U256 {
+        self.entry_burn_bps.get()
+    }
+
+    /// Returns (winner_bps, treasury_bps, burn_bps)
+    pub fn get_reward_split(&self) -> (U256, U256, U256) {
+        (self.winner_bps.get(), self.treasury_bps.get(), self.burn_bps.get())
+    }
+
+    /// Owner-only: set the whole prize split at once so it never sums above 100%
+    pub fn set_reward_split(
+        &mut self,
+        winner_bps: U256,
+        treasury_bps: U256,
+        burn_bps: U256,
+    ) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        let total = winner_bps
+            .checked_add(treasury_bps)
+            .and_then(|sum| sum.checked_add(burn_bps))
+            .unwrap_or(U256::MAX);
+        if total > U256::from(BPS_DENOMINATOR) {
+            return Err(Error::InvalidBps(InvalidBps { bps: total }));
+        }
+        // A native winner and the auto-withdraw cut are both paid from the same ETH pool
+        let native_total = winner_bps.saturating_add(self.auto_withdraw_bps.get());
+        if native_total > U256::from(BPS_DENOMINATOR) {
+            return Err(Error::InvalidBps(InvalidBps { bps: native_total }));
+        }
+        self.winner_bps.set(winner_bps);
+        self.treasury_bps.set(treasury_bps);
+        self.burn_bps.set(burn_bps);
         Ok(())
     }
-}
 
-// Note: We keep ownership management internal through `ownable`.
-fn get_extra_args_for_native_payment() -> Bytes {
-    // Encode extra args according to VRFV2PlusClient._argsToBytes()
-    // Format: abi.encodeWithSelector(EXTRA_ARGS_V1_TAG, extraArgs)
-    // where EXTRA_ARGS_V1_TAG = bytes4(keccak256("VRF ExtraArgsV1")) = 0x92fd1338
-    let mut extra_args_vec = Vec::new();
-    extra_args_vec.extend_from_slice(&[0x92, 0xfd, 0x13, 0x38]); // EXTRA_ARGS_V1_TAG
-    extra_args_vec.extend_from_slice(&[0x00; 28]); // Padding for struct alignment
-    extra_args_vec.extend_from_slice(&[0x00, 0x00, 0x00, 0x01]); // nativePayment: true
-    extra_args_vec.extend_from_slice(&[0x00; 28]); // Final padding
-    Bytes::from(extra_args_vec)
-}
+    /// View: what the winner of the current round would be credited, in reward token units or, when
+    /// paying natively, in wei
+    /// Follows `decide_winner`: the ETH paid in times `winner_bps` when paying natively, the fixed reward
+    /// in fixed mode, otherwise the paid-in pool (plus any auto-compounded carry-over) times `winner_bps`
+    pub fn effective_winner_prize(&self) -> U256 {
+        if self.flag(FLAG_PAY_WINNER_NATIVE) {
+            return self.current_native_pool.get() * self.winner_bps.get() / U256::from(BPS_DENOMINATOR);
+        }
+        if self.flag(FLAG_FIXED_REWARD) {
+            return self.fixed_reward_amount.get();
+        }
+        let mut reward = self.pool_in_reward_units();
+        if self.flag(FLAG_AUTO_COMPOUND) {
+            reward += self.carry_over.get();
+        }
+        reward * self.winner_bps.get() / U256::from(BPS_DENOMINATOR)
+    }
+
+    /// View: (min, max) winner prize for one-ticket entries, from a lone entrant up to `max_participants`
+    /// The max is `U256::MAX` when participants are uncapped; carry-over is left out
+    pub fn prize_range(&self) -> (U256, U256) {
+        if self.flag(FLAG_FIXED_REWARD) {
+            let fixed = self.fixed_reward_amount.get();
+            return (fixed, fixed);
+        }
+        let winner_bps = self.winner_bps.get();
+        let denominator = U256::from(BPS_DENOMINATOR);
+        let fee = self.lottery_entry_fee.get();
+        let min = self.scale_to_reward_decimals(fee) * winner_bps / denominator;
+        let max_participants = self.max_participants.get();
+        if max_participants == U256::ZERO {
+            return (min, U256::MAX);
+        }
+        let max = self.scale_to_reward_decimals(fee * max_participants) * winner_bps / denominator;
+        (min, max)
+    }
+
+    pub fn fixed_reward_mode(&self) -> bool {
+        self.flag(FLAG_FIXED_REWARD)
+    }
+
+    pub fn fixed_reward_amount(&self) -> U256 {
+        self.fixed_reward_amount.get()
+    }
+
+    /// When enabled, each winner is credited `fixed_reward_amount` reward tokens instead of the pool-based prize
+    pub fn set_fixed_reward_mode(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_FIXED_REWARD, enabled);
+        Ok(())
+    }
+
+    pub fn set_fixed_reward_amount(&mut self, amount: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.fixed_reward_amount.set(amount);
+        Ok(())
+    }
+
+    pub fn stream_rewards(&self) -> bool {
+        self.flag(FLAG_STREAM_REWARDS)
+    }
+
+    pub fn reward_stream_duration(&self) -> U256 {
+        self.reward_stream_duration.get()
+    }
+
+    /// When enabled (with a non-zero duration), winners receive their share through `claim_streamed_reward`
+    /// Treasury shares are still paid in one lump
+    pub fn set_stream_rewards(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_STREAM_REWARDS, enabled);
+        Ok(())
+    }
+
+    pub fn set_reward_stream_duration(&mut self, duration_seconds: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.reward_stream_duration.set(duration_seconds);
+        Ok(())
+    }
+
+    pub fn carry_over(&self) -> U256 {
+        self.carry_over.get()
+    }
+
+    pub fn auto_compound(&self) -> bool {
+        self.flag(FLAG_AUTO_COMPOUND)
+    }
+
+    /// When enabled, the share of each prize left unallocated by the reward split boosts the next round
+    /// Disabling it keeps the current `carry_over` for when it is enabled again
+    pub fn set_auto_compound(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_AUTO_COMPOUND, enabled);
+        Ok(())
+    }
+
+    pub fn winner_eligibility_hook(&self) -> Address {
+        self.winner_eligibility_hook.get()
+    }
+
+    /// Zero disables the check; otherwise an ineligible winner passes the prize to the next participant
+    pub fn set_winner_eligibility_hook(&mut self, hook: Address) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.winner_eligibility_hook.set(hook);
+        Ok(())
+    }
+
+    pub fn mix_block_data(&self) -> bool {
+        self.flag(FLAG_MIX_BLOCK_DATA)
+    }
+
+    /// Defense in depth: hash the VRF word with the fulfillment block's data before picking the winner, so a compromised
+    /// VRF alone can't choose it. The trade-off is that the outcome is no longer checkable from the VRF
+    /// proof alone; `verify_draw` then reports the mixed word
+    pub fn set_mix_block_data(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_MIX_BLOCK_DATA, enabled);
+        Ok(())
+    }
+
+    pub fn treasury(&self) -> Address {
+        self.treasury.get()
+    }
+
+    pub fn auto_withdraw_bps(&self) -> U256 {
+        self.auto_withdraw_bps.get()
+    }
+
+    /// Native ETH credited to the treasury by auto-withdrawals so far
+    pub fn total_auto_withdrawn(&self) -> U256 {
+        self.total_auto_withdrawn.get()
+    }
+
+    /// Share of each draw's native pool credited to the treasury, claimed via `claim_refund`
+    /// Together with `winner_bps` it may not exceed the whole pool
+    pub fn set_auto_withdraw_bps(&mut self, bps: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        let total = bps.saturating_add(self.winner_bps.get());
+        if total > U256::from(BPS_DENOMINATOR) {
+            return Err(Error::InvalidBps(InvalidBps { bps: total }));
+        }
+        self.auto_withdraw_bps.set(bps);
+        Ok(())
+    }
+
+    pub fn set_treasury(&mut self, treasury: Address) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.treasury.set(treasury);
+        Ok(())
+    }
+
+    pub fn set_entry_burn_bps(&mut self, bps: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        if bps > U256::from(BPS_DENOMINATOR) {
+            return Err(Error::InvalidBps(InvalidBps { bps }));
+        }
+        self.entry_burn_bps.set(bps);
+        Ok(())
+    }
+
+    /// Owner-only: burn `amount` of the reward tokens held by this contract (token-mode entry fees)
+    /// The current round's token entries and unclaimed token refunds are held back
+    pub fn burn_pool(&mut self, amount: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        let token_address = self.erc20_token_address.get();
+        let failed = |_| Error::TokenCallFailed(TokenCallFailed { token: token_address });
+        let erc20 = IERC20::new(token_address);
+        let contract_address = self.vm().contract_address();
+        let held = erc20.balance_of(&*self, contract_address).map_err(failed)?;
+        let token_pool = self.current_pool.get() - self.current_native_pool.get();
+        let reserved = token_pool + self.total_pending_token_refunds.get(token_address);
+        let balance = held.saturating_sub(reserved);
+        if amount > balance {
+            return Err(Error::InsufficientPoolBalance(InsufficientPoolBalance {
+                requested: amount,
+                balance,
+            }));
+        }
+        erc20.burn(&mut *self, amount).map_err(failed)?;
+        Ok(())
+    }
+
+    /// Owner-only removal of a participant (e.g. a sanctioned address) before the draw
+    /// Swap-removes the address and refunds what it paid in the asset it paid with, queueing the refund
+    /// for `claim_refund` / `claim_token_refund` if the push fails. The address can't re-enter this round
+    pub fn remove_participant(&mut self, who: Address) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+
+        let position = self.participant_position.get(who);
+        if position == U256::ZERO {
+            return Err(Error::NotParticipant(NotParticipant { account: who }));
+        }
+        let idx: usize = position.try_into().unwrap_or(0) - 1;
+        let len = self.participants.len();
+        let last = self.participants.get(len - 1).unwrap_or(Address::ZERO);
+        let tickets = self.ticket_count.get(who);
+        let last_tickets = self.ticket_count.get(last);
+
+        // Move the last participant (and its tickets) into the freed slot
+        self.fenwick_sub(idx + 1, tickets);
+        if idx != len - 1 {
+            self.fenwick_add(idx + 1, last_tickets);
+            self.fenwick_sub(len, last_tickets);
+            self.participant_position.setter(last).set(position);
+        }
+        if let Some(mut slot) = self.participants.setter(idx) {
+            slot.set(last);
+        }
+        let _ = self.participants.pop();
+        self.participant_position.setter(who).set(U256::ZERO);
+        self.ticket_count.setter(who).set(U256::ZERO);
+        self.total_tickets.set(self.total_tickets.get() - tickets);
+        self.removed_in_round.setter(who).set(self.round_number.get());
+
+        let asset = self.entry_asset.get(who);
+        let refund = self.entry_paid.get(who);
+        self.entry_asset.setter(who).set(Address::ZERO);
+        self.entry_paid.setter(who).set(U256::ZERO);
+        self.current_pool.set(self.current_pool.get() - refund);
+        if asset == Address::ZERO {
+            self.current_native_pool.set(self.current_native_pool.get() - refund);
+        }
+        self.refund_entry(who, asset, refund);
+
+        log(self.vm(), ParticipantRemoved { participant: who });
+        Ok(())
+    }
+
+    pub fn lottery_entry_fee(&self) -> U256 { // In Wei (Eth)
+        self.lottery_entry_fee.get()
+    }
+
+    pub fn set_lottery_entry_fee(&mut self, fee: U256) -> Result<(), Error> {// In Wei (Eth)
+        self.ownable.only_owner()?;
+        self.ensure_valid_entry_fee(fee)?;
+        self.lottery_entry_fee.set(fee);
+        Ok(())
+    }
+
+    pub fn free_entry_allowed(&self) -> bool {
+        self.flag(FLAG_FREE_ENTRY)
+    }
+
+    /// Opt in to intentional zero-fee lotteries; otherwise a zero entry fee is rejected
+    pub fn set_free_entry_allowed(&mut self, allowed: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_FREE_ENTRY, allowed);
+        Ok(())
+    }
+
+    pub fn lottery_interval_hours(&self) -> U256 {
+        self.lottery_interval_hours.get()
+    }
+
+    /// At least `MIN_LOTTERY_INTERVAL_HOURS`
+    pub fn set_lottery_interval_hours(&mut self, interval_hours: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        ensure_min_interval(interval_hours)?;
+        self.lottery_interval_hours.set(interval_hours);
+        Ok(())
+    }
+
+    pub fn num_words(&self) -> U256 {
+        self.num_words.get()
+    }
+
+    /// Bounded by `max_num_words` to keep the VRF price and callback workload in check
+    pub fn set_num_words(&mut self, num_words: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        if num_words == U256::ZERO {
+            return Err(Error::ZeroNumWords(ZeroNumWords {}));
+        }
+        let max = self.max_num_words.get();
+        if num_words > max {
+            return Err(Error::NumWordsAboveMax(NumWordsAboveMax {
+                requested: num_words,
+                max,
+            }));
+        }
+        self.num_words.set(num_words);
+        Ok(())
+    }
+
+    /// Owner-only: set callback gas limit, confirmations and word count together, all or nothing
+    pub fn set_vrf_config(&mut self, gas_limit: u32, confirmations: u16, words: u32) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        let max = self.max_num_words.get();
+        if U256::from(words) > max {
+            return Err(Error::NumWordsAboveMax(NumWordsAboveMax {
+                requested: U256::from(words),
+                max,
+            }));
+        }
+        if gas_limit == 0
+            || gas_limit > MAX_CALLBACK_GAS_LIMIT
+            || confirmations == 0
+            || confirmations > MAX_REQUEST_CONFIRMATIONS
+            || words == 0
+        {
+            return Err(Error::InvalidVrfConfig(InvalidVrfConfig {
+                callback_gas_limit: gas_limit,
+                request_confirmations: confirmations,
+                num_words: words,
+            }));
+        }
+        self.callback_gas_limit.set(U256::from(gas_limit));
+        self.request_confirmations.set(U256::from(confirmations));
+        self.num_words.set(U256::from(words));
+        log(
+            self.vm(),
+            VrfConfigChanged {
+                callbackGasLimit: gas_limit,
+                requestConfirmations: confirmations,
+                numWords: words,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn max_num_words(&self) -> U256 {
+        self.max_num_words.get()
+    }
+
+    /// May not drop below the current `num_words`
+    pub fn set_max_num_words(&mut self, max_num_words: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        let num_words = self.num_words.get();
+        if max_num_words < num_words {
+            return Err(Error::NumWordsAboveMax(NumWordsAboveMax {
+                requested: num_words,
+                max: max_num_words,
+            }));
+        }
+        self.max_num_words.set(max_num_words);
+        Ok(())
+    }
+
+    pub fn max_request_price(&self) -> U256 {
+        self.max_request_price.get()
+    }
+
+    pub fn set_max_request_price(&mut self, max_price: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.max_request_price.set(max_price);
+        Ok(())
+    }
+
+    pub fn early_entry_bonus(&self) -> bool {
+        self.flag(FLAG_EARLY_ENTRY_BONUS)
+    }
+
+    /// Owner-only: how many of the newest draws `prune_old_draws` keeps
+    pub fn set_max_retained_draws(&mut self, max_retained: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.max_retained_draws.set(max_retained);
+        Ok(())
+    }
+
+    pub fn require_eoa(&self) -> bool {
+        self.flag(FLAG_REQUIRE_EOA)
+    }
+
+    /// Anti-bot toggle: reject entrants that have contract code
+    pub fn set_require_eoa(&mut self, required: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_REQUIRE_EOA, required);
+        Ok(())
+    }
+
+    pub fn pay_winner_native(&self) -> bool {
+        self.flag(FLAG_PAY_WINNER_NATIVE)
+    }
+
+    /// Pay the winner's share of the ETH paid in instead of minting reward tokens, claimed via `claim_refund`
+    pub fn set_pay_winner_native(&mut self, native: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_PAY_WINNER_NATIVE, native);
+        Ok(())
+    }
+
+    pub fn one_entry_per_block(&self) -> bool {
+        self.flag(FLAG_ONE_ENTRY_PER_BLOCK)
+    }
+
+    /// Anti-spam toggle: reject a second entry (or ticket top-up) from the same address in one block
+    pub fn set_one_entry_per_block(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_ONE_ENTRY_PER_BLOCK, enabled);
+        Ok(())
+    }
+
+    pub fn entry_cooldown_seconds(&self) -> U256 {
+        self.entry_cooldown_seconds.get()
+    }
+
+    /// Minimum seconds between an address's entry and its next entry or ticket top-up; zero disables it
+    pub fn set_entry_cooldown_seconds(&mut self, cooldown: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.entry_cooldown_seconds.set(cooldown);
+        Ok(())
+    }
+
+    /// Toggle the early-entrant weighting in winner selection (uniform when off)
+    pub fn set_early_entry_bonus(&mut self, enabled: bool) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.set_flag(FLAG_EARLY_ENTRY_BONUS, enabled);
+        Ok(())
+    }
+
+    pub fn get_entry_time(&self, account: Address) -> U256 {
+        self.s_entry_time.get(account)
+    }
+
+    /// Participants are cleared at every draw, so this is the size of the current round
+    pub fn current_round_participant_count(&self) -> U256 {
+        U256::from(self.participants.len())
+    }
+
+    /// View: current-round participants who entered at or after `timestamp`
+    pub fn participants_joined_since(&self, timestamp: U256) -> U256 {
+        let joined = (0..self.participants.len())
+            .filter_map(|i| self.participants.get(i))
+            .filter(|participant| self.s_entry_time.get(*participant) >= timestamp)
+            .count();
+        U256::from(joined)
+    }
+
+    /// View: entry fee in native units
+    /// Converts the token fee through the configured price feed, falling back to the stored native fee
+    pub fn entry_fee_in_native(&self) -> U256 {
+        let feed_address = self.price_feed.get();
+        if feed_address == Address::ZERO {
+            return self.entry_fee_native.get();
+        }
+        let feed = IChainlinkAggregator::new(feed_address);
+        let answer = feed.latest_answer(self);
+        let decimals = feed.decimals(self);
+        match (answer, decimals) {
+            (Ok(answer), Ok(decimals)) if answer.is_positive() => {
+                self.lottery_entry_fee.get() * answer.into_raw()
+                    / U256::from(10).pow(U256::from(decimals))
+            }
+            _ => self.entry_fee_native.get(),
+        }
+    }
+
+    pub fn entry_fee_native(&self) -> U256 {
+        self.entry_fee_native.get()
+    }
+
+    pub fn set_entry_fee_native(&mut self, fee: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.entry_fee_native.set(fee);
+        Ok(())
+    }
+
+    pub fn price_feed(&self) -> Address {
+        self.price_feed.get()
+    }
+
+    pub fn set_price_feed(&mut self, feed: Address) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.price_feed.set(feed);
+        Ok(())
+    }
+
+    pub fn max_participants(&self) -> U256 {
+        self.max_participants.get()
+    }
+
+    /// Zero means no cap
+    pub fn set_max_participants(&mut self, max_participants: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.max_participants.set(max_participants);
+        Ok(())
+    }
+
+    pub fn min_token_balance_to_enter(&self) -> U256 {
+        self.min_token_balance_to_enter.get()
+    }
+
+    /// Zero lets anyone enter; otherwise entrants must hold this many reward tokens
+    pub fn set_min_token_balance_to_enter(&mut self, min_balance: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.min_token_balance_to_enter.set(min_balance);
+        Ok(())
+    }
+
+    pub fn max_contract_balance(&self) -> U256 {
+        self.max_contract_balance.get()
+    }
+
+    /// Zero means no cap; wrapper refunds are always accepted
+    pub fn set_max_contract_balance(&mut self, max_balance: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.max_contract_balance.set(max_balance);
+        Ok(())
+    }
+
+    pub fn min_operational_balance(&self) -> U256 {
+        self.min_operational_balance.get()
+    }
+
+    /// Zero disables the floor; `sweep_dust` keeps the larger of it and the next draw's cost
+    pub fn set_min_operational_balance(&mut self, min_balance: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.min_operational_balance.set(min_balance);
+        Ok(())
+    }
+
+    pub fn min_participants(&self) -> U256 {
+        self.min_participants.get()
+    }
+
+    pub fn set_min_participants(&mut self, min_participants: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.min_participants.set(min_participants);
+        Ok(())
+    }
+
+    /// Crate version this contract was built from
+    pub fn version(&self) -> String {
+        VERSION.into()
+    }
+
+    pub fn contract_type(&self) -> String {
+        "VrfConsumer".into()
+    }
+
+    pub fn lottery_name(&self) -> String {
+        self.lottery_name.get_string()
+    }
+
+    /// Owner-only: rename the lottery after deployment
+    pub fn set_lottery_name(&mut self, name: String) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.lottery_name.set_str(name);
+        Ok(())
+    }
+
+    /// EIP-165: the consumer interface (`CONSUMER_INTERFACE_ID`) and EIP-165 itself
+    pub fn supports_interface(&self, interface_id: FixedBytes<4>) -> bool {
+        interface_id.0 == CONSUMER_INTERFACE_ID || interface_id.0 == ERC165_INTERFACE_ID
+    }
+
+    /// View: full mutable lottery configuration in a single snapshot
+    /// Returns (entry_fee, interval_hours, erc20_token, callback_gas_limit, request_confirmations, num_words, max_num_words, initial_grace_seconds, min_participants, max_participants, winner_bps, treasury_bps, burn_bps)
+    pub fn get_full_config(&self) -> FullConfig {
+        (
+            self.lottery_entry_fee.get(),
+            self.lottery_interval_hours.get(),
+            self.erc20_token_address.get(),
+            self.callback_gas_limit.get(),
+            self.request_confirmations.get(),
+            self.num_words.get(),
+            self.max_num_words.get(),
+            self.initial_grace_seconds.get(),
+            self.min_participants.get(),
+            self.max_participants.get(),
+            self.winner_bps.get(),
+            self.treasury_bps.get(),
+            self.burn_bps.get(),
+        )
+    }
+
+    /// View: ABI-encoded `(vrf_v2_plus_wrapper, owner, lottery_name, full_config)` to deploy a copy of this lottery
+    /// The leading three are the constructor args; replay `full_config` (laid out as in `get_full_config`)
+    /// through the setters once deployed
+    pub fn get_deploy_config(&self) -> stylus_sdk::abi::Bytes {
+        let wrapper = self.i_vrf_v2_plus_wrapper.get();
+        (wrapper, self.ownable.owner(), self.lottery_name(), self.get_full_config())
+            .abi_encode_params()
+            .into()
+    }
+
+    pub fn genesis_timestamp(&self) -> U256 {
+        self.genesis_timestamp.get()
+    }
+
+    pub fn initial_grace_seconds(&self) -> U256 {
+        self.initial_grace_seconds.get()
+    }
+
+    pub fn set_initial_grace_seconds(&mut self, grace_seconds: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.initial_grace_seconds.set(grace_seconds);
+        Ok(())
+    }
+
+    /// Receive function equivalent - handles incoming ETH
+    #[receive]
+    #[payable]
+    pub fn receive(&mut self) -> Result<(), Vec<u8>> {
+        // Overpayment refunded by the wrapper tops the draw funding back up rather than being a deposit
+        if self.vm().msg_sender() == self.i_vrf_v2_plus_wrapper.get() {
+            log(self.vm(), VrfRefund { amount: self.vm().msg_value() });
+            return Ok(());
+        }
+        self.ensure_within_balance_cap()?;
+        // Skip logging dust transfers below the configured threshold
+        if self.vm().msg_value() < self.min_log_value.get() {
+            return Ok(());
+        }
+        log(
+            self.vm(),
+            Received {
+                sender: self.vm().msg_sender(),
+                value: self.vm().msg_value(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn min_log_value(&self) -> U256 {
+        self.min_log_value.get()
+    }
+
+    pub fn set_min_log_value(&mut self, min_value: U256) -> Result<(), Error> {
+        self.ownable.only_owner()?;
+        self.min_log_value.set(min_value);
+        Ok(())
+    }
+}
+
+/// Test-mode only: draws from a caller-provided seed instead of a VRF request
+/// Routed only when built with the `test-mode` feature, which can't target wasm32
+#[cfg(feature = "signed-actions")]
+pub trait ISignedActions {
+    fn signed_action_nonce(&self) -> U256;
+    fn domain_separator(&self) -> B256;
+    fn execute_signed(
+        &mut self,
+        action: stylus_sdk::abi::Bytes,
+        nonce: U256,
+        signature: stylus_sdk::abi::Bytes,
+    ) -> Result<(), Vec<u8>>;
+}
+
+#[cfg(feature = "signed-actions")]
+#[public]
+impl ISignedActions for VrfConsumer {
+    /// Next nonce the owner must sign for `execute_signed`
+    fn signed_action_nonce(&self) -> U256 {
+        self.signed_action_nonces.get(self.ownable.owner())
+    }
+
+    fn domain_separator(&self) -> B256 {
+        self.domain_separator_v4()
+    }
+
+    /// Relay an owner setter authorized off-chain by an EIP-712 `SignedAction(bytes action,uint256 nonce)`
+    /// `action` is the setter's calldata and `signature` is the 65-byte r || s || v
+    fn execute_signed(
+        &mut self,
+        action: stylus_sdk::abi::Bytes,
+        nonce: U256,
+        signature: stylus_sdk::abi::Bytes,
+    ) -> Result<(), Vec<u8>> {
+        let owner = self.ownable.owner();
+        if nonce != self.signed_action_nonces.get(owner) {
+            return Err(b"Invalid nonce".to_vec());
+        }
+        if signature.len() != 65 {
+            return Err(b"Invalid signature length".to_vec());
+        }
+
+        let action_hash = self.vm().native_keccak256(&action);
+        let struct_hash = self.vm().native_keccak256(
+            &[SIGNED_ACTION_TYPEHASH, action_hash.0, nonce.to_be_bytes::<32>()].concat(),
+        );
+        let digest = self.hash_typed_data_v4(struct_hash);
+        let r = B256::from_slice(&signature[0..32]);
+        let s = B256::from_slice(&signature[32..64]);
+        #[allow(deprecated)]
+        let signer = ecdsa::recover(OldCall::new(), digest, signature[64], r, s)
+            .map_err(|_| b"Invalid signature".to_vec())?;
+        if signer != owner {
+            return Err(b"Signer is not owner".to_vec());
+        }
+
+        self.signed_action_nonces.setter(owner).set(nonce + U256::from(1));
+        self.dispatch_signed_action(&action)
+    }
+}
+
+#[cfg(feature = "test-mode")]
+pub trait ISeededDraw {
+    fn request_random_words_with_seed(&mut self, seed: U256) -> Result<U256, Vec<u8>>;
+}
+
+#[cfg(feature = "test-mode")]
+#[public]
+impl ISeededDraw for VrfConsumer {
+    /// Run a full draw with `seed` as its only random word
+    fn request_random_words_with_seed(&mut self, seed: U256) -> Result<U256, Vec<u8>> {
+        self.seeded_draw(seed)
+    }
+}
+
+// Internal helpers kept outside `#[public]` so they are not exposed in the ABI
+impl VrfConsumer {
+    /// Credit `auto_withdraw_bps` of the ETH paid in to the treasury; nothing is pushed inside the callback
+    fn credit_auto_withdraw(&mut self, native_pool: U256) {
+        let treasury = self.treasury.get();
+        let bps = self.auto_withdraw_bps.get();
+        if treasury == Address::ZERO || bps == U256::ZERO {
+            return;
+        }
+        let cut = native_pool * bps / U256::from(BPS_DENOMINATOR);
+        if cut == U256::ZERO {
+            return;
+        }
+        self.queue_refund(treasury, cut);
+        self.total_auto_withdrawn.set(self.total_auto_withdrawn.get() + cut);
+    }
+
+    /// Decide the winner of `request_id`, snapshot it for `verify_draw` and reopen entries
+    /// `draw_word` picks the winner (already mixed when block data mixing is on); `random_words` are only logged
+    fn settle_draw(&mut self, request_id: U256, draw_word: Option<U256>, random_words: Vec<U256>) -> Address {
+        let fulfilled_value = draw_word.unwrap_or(U256::ZERO);
+        let draw_ticket_count = self.total_tickets.get();
+        let participant_count = U256::from(self.participants.len());
+        let prize = self.effective_winner_prize();
+        let native_pool = self.current_native_pool.get();
+        let winner_address = self.decide_winner(request_id, draw_word.as_slice());
+        // Without a winner the round stays open, so its pool is only cut once it is settled
+        if winner_address != Address::ZERO {
+            self.credit_auto_withdraw(native_pool);
+        }
+        // self.last_winner.set(winner_address);
+        self.s_draw_random_word.setter(request_id).set(fulfilled_value);
+        self.s_draw_ticket_count.setter(request_id).set(draw_ticket_count);
+        self.s_draw_winner.setter(request_id).set(winner_address);
+
+        log(
+            self.vm(), // emit the event in the current contract's execution context
+            RequestFulfilled {
+                requestId: request_id,
+                randomWords: random_words,
+                winner: winner_address,
+            },
+        );
+        // Self-contained record of the draw for indexers
+        log(
+            self.vm(),
+            DrawCompleted {
+                requestId: request_id,
+                winner: winner_address,
+                winnerIndex: self.s_winner_index.get(request_id),
+                randomWord: fulfilled_value,
+                prize: if winner_address == Address::ZERO { U256::ZERO } else { prize },
+                participantCount: participant_count,
+            },
+        );
+        if !self.flag(FLAG_MANUAL_RESTART) {
+            self.set_accepting(true); // accept new participants again
+        }
+        winner_address
+    }
+
+    /// keccak256(random_word, block_number, block_timestamp)
+    /// Stylus exposes no prevrandao (constant on Arbitrum anyway), so the block number stands in for it
+    fn mixed_with_block_data(&self, random_word: U256) -> U256 {
+        let block_number = U256::from(self.vm().block_number());
+        let timestamp = U256::from(self.vm().block_timestamp());
+        let preimage = [
+            random_word.to_be_bytes::<32>(),
+            block_number.to_be_bytes::<32>(),
+            timestamp.to_be_bytes::<32>(),
+        ]
+        .concat();
+        U256::from_be_bytes(self.vm().native_keccak256(&preimage).0)
+    }
+
+    /// Internal function to decide the winner
+    fn decide_winner(&mut self, request_id: U256, random_words: &[U256]) -> Address {
+        if self.participants.is_empty() || random_words.is_empty() {
+            return Address::ZERO;
+        }
+
+        let total_tickets = self.total_tickets.get();
+        let len = self.participants.len();
+        let idx = if self.flag(FLAG_EARLY_ENTRY_BONUS) {
+            self.weighted_winner_index(random_words[0])
+        } else {
+            // With one ticket each this is exactly `random_word % len`
+            self.ticket_winner_index(random_words[0] % total_tickets)
+        };
+        self.s_draw_drawn_index.setter(request_id).set(U256::from(idx));
+        self.s_draw_participant_count.setter(request_id).set(U256::from(len));
+    
+        let rerolls = self.eligibility_rerolls(idx);
+        let winner_idx = rerolls.map(|rerolls| rerolled_index(idx, rerolls, len));
+        let winner = winner_idx
+            .and_then(|i| self.participants.get(i))
+            .unwrap_or(Address::ZERO);
+        if let (Some(i), Some(rerolls)) = (winner_idx, rerolls) {
+            self.s_winner_index.setter(request_id).set(U256::from(i));
+            self.s_draw_rerolls.setter(request_id).set(U256::from(rerolls));
+        }
+    
+        if winner != Address::ZERO {
+            // Pull model: winner claims via `claim_reward`, so a bad recipient can't block fulfillment
+            // A native prize is always the ETH paid in, so it takes precedence over a fixed token reward
+            if self.flag(FLAG_FIXED_REWARD) && !self.flag(FLAG_PAY_WINNER_NATIVE) {
+                // Guaranteed prize: independent of the pool, the reward split and any carry-over
+                self.credit_winner(winner, self.fixed_reward_amount.get());
+            } else {
+                let auto_compound = self.flag(FLAG_AUTO_COMPOUND);
+                let mut reward = self.pool_in_reward_units();
+                if auto_compound {
+                    reward += self.carry_over.get();
+                }
+                // let reward = self.lottery_entry_fee.get().checked_mul(U256::from(len)).unwrap_or(U256::MAX); // TODO: Above method can overflow, but contract is too big to deploy if I handle it; risk possibility of user getting low rewards for now
+                let denominator = U256::from(BPS_DENOMINATOR);
+                let winner_share = reward * self.winner_bps.get() / denominator;
+                if self.flag(FLAG_PAY_WINNER_NATIVE) {
+                    // Only ETH actually paid in backs a native prize; the winner pulls it via `claim_refund`
+                    let native_prize = self.current_native_pool.get() * self.winner_bps.get() / denominator;
+                    self.note_win(winner, native_prize);
+                    self.queue_refund(winner, native_prize);
+                } else {
+                    self.credit_winner(winner, winner_share);
+                }
+
+                let treasury = self.treasury.get();
+                let treasury_share = reward * self.treasury_bps.get() / denominator;
+                let mut distributed = winner_share + reward * self.burn_bps.get() / denominator;
+                if treasury != Address::ZERO && treasury_share != U256::ZERO {
+                    let pending = self.pending_rewards.get(treasury);
+                    self.pending_rewards.setter(treasury).set(pending + treasury_share);
+                    distributed += treasury_share;
+                }
+                // Without auto-compounding the carry-over stays parked until it is enabled again
+                if auto_compound {
+                    self.carry_over.set(reward - distributed);
+                }
+            }
+            self.record_recent_winner(winner);
+            self.clear_participants();
+        }
+        winner
+    }
+
+    /// Re-rolls past `idx` until the eligibility hook accepts a participant (none within the re-roll bound)
+    fn eligibility_rerolls(&self, idx: usize) -> Option<usize> {
+        let hook_address = self.winner_eligibility_hook.get();
+        if hook_address == Address::ZERO {
+            return Some(0);
+        }
+        let hook = IWinnerEligibilityHook::new(hook_address);
+        let len = self.participants.len();
+        (0..=MAX_ELIGIBILITY_REROLLS.min(len - 1)).find(|&rerolls| {
+            let candidate = self.participants.get(rerolled_index(idx, rerolls, len)).unwrap_or(Address::ZERO);
+            // A failing hook must not block the draw
+            hook.is_eligible(self, candidate).unwrap_or(true)
+        })
+    }
+
+    /// Lifetime stats for `winner`; `amount` is in wei when the prize was paid natively
+    fn note_win(&mut self, winner: Address, amount: U256) {
+        let won = self.total_won.get(winner);
+        self.total_won.setter(winner).set(won + amount);
+        let wins = self.win_count.get(winner);
+        self.win_count.setter(winner).set(wins + U256::from(1));
+    }
+
+    /// Credit a prize as a lump sum, or as a new stream when streaming is on
+    /// An existing stream is settled first: its vested part becomes claimable and the rest joins the new stream
+    fn credit_winner(&mut self, winner: Address, amount: U256) {
+        self.note_win(winner, amount);
+
+        let duration = self.reward_stream_duration.get();
+        if !self.flag(FLAG_STREAM_REWARDS) || duration == U256::ZERO {
+            let pending = self.pending_rewards.get(winner);
+            self.pending_rewards.setter(winner).set(pending + amount);
+            return;
+        }
+        let vested = self.vested_stream(winner);
+        let unclaimed = vested - self.stream_claimed.get(winner);
+        if unclaimed != U256::ZERO {
+            let pending = self.pending_rewards.get(winner);
+            self.pending_rewards.setter(winner).set(pending + unclaimed);
+        }
+        let unvested = self.stream_total.get(winner) - vested;
+        let now = U256::from(self.vm().block_timestamp());
+        self.stream_total.setter(winner).set(unvested + amount);
+        self.stream_claimed.setter(winner).set(U256::ZERO);
+        self.stream_start.setter(winner).set(now);
+        self.stream_duration.setter(winner).set(duration);
+    }
+
+    /// Linearly vested part of `account`'s stream, claimed or not
+    fn vested_stream(&self, account: Address) -> U256 {
+        let duration = self.stream_duration.get(account);
+        if duration == U256::ZERO {
+            return U256::ZERO;
+        }
+        let elapsed = U256::from(self.vm().block_timestamp())
+            .saturating_sub(self.stream_start.get(account))
+            .min(duration);
+        self.stream_total.get(account) * elapsed / duration
+    }
+
+    fn flag(&self, bit: u64) -> bool {
+        self.flags.get() & U256::from(bit) != U256::ZERO
+    }
+
+    fn set_flag(&mut self, bit: u64, on: bool) {
+        let flags = self.flags.get();
+        let updated = if on { flags | U256::from(bit) } else { flags & !U256::from(bit) };
+        if updated != flags {
+            self.flags.set(updated);
+        }
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.flag(FLAG_ACCEPTING)
+    }
+
+    fn set_accepting(&mut self, accepting: bool) {
+        self.set_flag(FLAG_ACCEPTING, accepting);
+    }
+
+    fn is_withdrawing(&self) -> bool {
+        self.flag(FLAG_WITHDRAWING)
+    }
+
+    fn set_withdrawing(&mut self, withdrawing: bool) {
+        self.set_flag(FLAG_WITHDRAWING, withdrawing);
+    }
+
+    /// Self-fulfilling draw: the request is fulfilled immediately with `seed` as the only word,
+    /// so the winner is `participants[seed % len]`
+    #[cfg(feature = "test-mode")]
+    fn seeded_draw(&mut self, seed: U256) -> Result<U256, Vec<u8>> {
+        if let Some(reason) = self.draw_blocked_reason() {
+            return Err(reason.to_vec());
+        }
+        self.ensure_native_prize_funded(U256::ZERO)?;
+
+        let now = U256::from(self.vm().block_timestamp());
+        let request_id = U256::from_be_bytes(
+            self.vm()
+                .native_keccak256(&[seed.to_be_bytes::<32>(), now.to_be_bytes::<32>()].concat())
+                .0,
+        );
+        self.s_request_timestamp.setter(request_id).set(now);
+        self.record_request_pool(request_id);
+        self.s_request_num_words.setter(request_id).set(U32::from(1));
+        self.request_ids.push(request_id);
+        self.last_request_timestamp.set(now);
+
+        self.fulfill_random_words(request_id, vec![seed])?;
+        Ok(request_id)
+    }
+
+    /// A native prize is queued for `claim_refund` at draw time, so the balance left after `spend`
+    /// and the refunds already owed must cover it before randomness is requested
+    fn ensure_native_prize_funded(&self, spend: U256) -> Result<(), Vec<u8>> {
+        if !self.flag(FLAG_PAY_WINNER_NATIVE) {
+            return Ok(());
+        }
+        let balance = self.vm().balance(self.vm().contract_address());
+        let available = balance.saturating_sub(self.total_pending_refunds.get()).saturating_sub(spend);
+        if available < self.effective_winner_prize() {
+            return Err(b"Native prize unfunded".to_vec());
+        }
+        Ok(())
+    }
+
+    fn record_request_pool(&mut self, request_id: U256) {
+        self.s_request_pool.setter(request_id).set(self.current_pool.get());
+    }
+
+    /// Preconditions for triggering a draw, shared by `request_random_words` and `can_request_draw`
+    fn draw_blocked_reason(&self) -> Option<&'static [u8]> {
+        if self.flag(FLAG_DRAWS_PAUSED) {
+            return Some(b"Draws paused");
+        }
+        let now = U256::from(self.vm().block_timestamp());
+        // let interval_secs = self.lottery_interval_hours.get().checked_mul(U256::from(3600)).ok_or_else(|| b"Interval overflow".to_vec())?; // TODO: Below method can overflow, temporarily unhandled for deployment purposes
+        if now
+        < self.last_request_timestamp.get() + self.lottery_interval_hours.get() * U256::from(3600)
+        {
+            return Some(b"Too soon to resolve lottery");
+        }
+        // First draw has to wait out the sign-up window after deployment
+        if self.last_request_timestamp.get() == U256::ZERO
+            && now < self.genesis_timestamp.get() + self.initial_grace_seconds.get()
+        {
+            return Some(b"Grace period active");
+        }
+        if U256::from(self.participants.len()) < self.min_participants.get() {
+            return Some(b"Not enough participants");
+        }
+        // Nobody to draw from, don't pay the wrapper for it
+        if self.participants.is_empty() {
+            return Some(b"No participants");
+        }
+        if self.flag(FLAG_DRAW_DEFERRED) {
+            return Some(b"Draw awaiting finalization");
+        }
+        None
+    }
+
+    /// Push into the recent winners ring buffer, overwriting the oldest entry once full
+    fn record_recent_winner(&mut self, winner: Address) {
+        if self.recent_winners.len() < RECENT_WINNERS_CAPACITY {
+            self.recent_winners.push(winner);
+            return;
+        }
+        let head: usize = self.recent_winners_head.get().try_into().unwrap_or(0);
+        if let Some(mut slot) = self.recent_winners.setter(head) {
+            slot.set(winner);
+        }
+        self.recent_winners_head.set(U256::from((head + 1) % RECENT_WINNERS_CAPACITY));
+    }
+
+    /// Best-effort probe: the token grants this contract MINTER_ROLE
+    fn can_mint_token(&mut self, token_address: Address) -> bool {
+        if token_address == Address::ZERO || self.vm().code_size(token_address) == 0 {
+            return false;
+        }
+        let minter_role = self.vm().native_keccak256(b"MINTER_ROLE");
+        let contract_address = self.vm().contract_address();
+        IERC20::new(token_address)
+            .has_role(&mut *self, minter_role, contract_address)
+            .unwrap_or(false)
+    }
+
+    /// Token's `decimals()`, assuming 18 when unset or not implemented
+    fn query_token_decimals(&mut self, token_address: Address) -> u8 {
+        if token_address == Address::ZERO || self.vm().code_size(token_address) == 0 {
+            return NATIVE_DECIMALS;
+        }
+        IERC20::new(token_address)
+            .decimals(&mut *self)
+            .unwrap_or(NATIVE_DECIMALS)
+    }
+
+    /// Convert a wei-denominated amount into reward token units
+    fn scale_to_reward_decimals(&self, amount: U256) -> U256 {
+        let decimals = self.reward_token_decimals.get();
+        let native = U256::from(NATIVE_DECIMALS);
+        if decimals >= native {
+            amount * U256::from(10).pow(decimals - native)
+        } else {
+            amount / U256::from(10).pow(native - decimals)
+        }
+    }
+
+    /// The round's pool in reward token units: the ETH paid in is scaled from wei, token entries
+    /// are already in the token's units
+    fn pool_in_reward_units(&self) -> U256 {
+        let native = self.current_native_pool.get();
+        self.scale_to_reward_decimals(native) + (self.current_pool.get() - native)
+    }
+
+    /// A zero entry fee is only valid once free entry has been allowed
+    fn ensure_valid_entry_fee(&self, fee: U256) -> Result<(), Error> {
+        if fee == U256::ZERO && !self.flag(FLAG_FREE_ENTRY) {
+            return Err(Error::ZeroEntryFee(ZeroEntryFee {}));
+        }
+        Ok(())
+    }
+
+    fn withdraw_each(&mut self, tokens: &[Address], amounts: &[U256]) -> Result<(), Vec<u8>> {
+        let owner = self.ownable.owner();
+        for (&token, &amount) in tokens.iter().zip(amounts) {
+            if token == Address::ZERO {
+                self.ensure_above_operational_floor(amount)?;
+                self.vm().call(&Call::new().value(amount), owner, &[])?;
+            } else if !IERC20::new(token).transfer(&mut *self, owner, amount)? {
+                return Err(b"Token transfer failed".to_vec());
+            }
+        }
+        Ok(())
+    }
+
+    /// Pull the entry fee from `participant` via `transferFrom`, burn the configured share and register them
+    fn enter_with_token(&mut self, participant: Address) -> Result<(), Vec<u8>> {
+        let token_address = self.erc20_token_address.get();
+        if token_address == Address::ZERO {
+            return Err(b"Token not set".to_vec());
+        }
+        self.ensure_can_enter(participant)?;
+        self.note_entry_block(participant)?;
+
+        let entry_fee = self.lottery_entry_fee.get();
+        if entry_fee == U256::ZERO && !self.flag(FLAG_FREE_ENTRY) {
+            return Err(b"Fee not set".to_vec());
+        }
+
+        let erc20 = IERC20::new(token_address);
+        let contract_address = self.vm().contract_address();
+        if !erc20.transfer_from(&mut *self, participant, contract_address, entry_fee)? {
+            return Err(b"Token transfer failed".to_vec());
+        }
+
+        // Entries pushed via `register_token_entry` skip the burn: the token is mid-transfer and cannot be re-entered
+        let burn_amount = entry_fee * self.entry_burn_bps.get() / U256::from(BPS_DENOMINATOR);
+        if burn_amount != U256::ZERO {
+            erc20.burn(&mut *self, burn_amount)?;
+        }
+
+        self.record_entry(participant);
+        self.note_payment(participant, token_address, entry_fee - burn_amount);
+        Ok(())
+    }
+
+    fn participants_with_tickets_page(&self, offset: usize, limit: usize) -> (Vec<Address>, Vec<U256>) {
+        let len = self.participants.len();
+        let start = offset.min(len);
+        let end = start.saturating_add(limit).min(len);
+        let mut accounts = Vec::with_capacity(end - start);
+        let mut tickets = Vec::with_capacity(end - start);
+        for i in start..end {
+            let account = self.participants.get(i).unwrap_or(Address::ZERO);
+            tickets.push(self.ticket_count.get(account));
+            accounts.push(account);
+        }
+        (accounts, tickets)
+    }
+
+    /// Whether `max_participants` is configured and reached
+    fn is_full(&self) -> bool {
+        let max = self.max_participants.get();
+        max != U256::ZERO && U256::from(self.participants.len()) >= max
+    }
+
+    /// Per-address entry rate limits: the `entry_cooldown_seconds` since the recorded entry time, then
+    /// one entry per block, only tracked while `one_entry_per_block` is enabled
+    fn note_entry_block(&mut self, participant: Address) -> Result<(), Vec<u8>> {
+        if let Some((_, reason)) = self.rate_limit_rejection(participant) {
+            return Err(reason.to_vec());
+        }
+        if self.flag(FLAG_ONE_ENTRY_PER_BLOCK) {
+            let block = U256::from(self.vm().block_number());
+            self.last_entry_block.setter(participant).set(block);
+        }
+        Ok(())
+    }
+
+    /// The rate limit `note_entry_block` enforces, as a `participation_quote` code and revert reason
+    fn rate_limit_rejection(&self, participant: Address) -> Option<(u8, &'static [u8])> {
+        let cooldown = self.entry_cooldown_seconds.get();
+        let entered_at = self.s_entry_time.get(participant);
+        if cooldown != U256::ZERO
+            && entered_at != U256::ZERO
+            && U256::from(self.vm().block_timestamp()) < entered_at + cooldown
+        {
+            return Some((STATUS_COOLDOWN, b"Cooldown active"));
+        }
+        let block = U256::from(self.vm().block_number());
+        if self.flag(FLAG_ONE_ENTRY_PER_BLOCK) && self.last_entry_block.get(participant) == block {
+            return Some((STATUS_SAME_BLOCK, b"One entry per block"));
+        }
+        None
+    }
+
+    /// The incoming value is already part of the balance when a payable method runs
+    fn ensure_within_balance_cap(&self) -> Result<(), Vec<u8>> {
+        let cap = self.max_contract_balance.get();
+        if cap != U256::ZERO && self.vm().balance(self.vm().contract_address()) > cap {
+            return Err(b"Balance cap reached".to_vec());
+        }
+        Ok(())
+    }
+
+    fn ensure_above_operational_floor(&self, amount: U256) -> Result<(), Vec<u8>> {
+        let floor = self.min_operational_balance.get();
+        let balance = self.vm().balance(self.vm().contract_address());
+        if floor != U256::ZERO && balance.saturating_sub(amount) < floor {
+            return Err(b"Below operational balance".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Entry checks shared by every participation path
+    fn ensure_can_enter(&self, participant: Address) -> Result<(), Vec<u8>> {
+        match self.entry_rejection(participant) {
+            Some((_, reason)) => Err(reason.to_vec()),
+            None => Ok(()),
+        }
+    }
+
+    /// Why `participant` can't enter as a new participant, as a `participation_quote` code and revert reason
+    fn entry_rejection(&self, participant: Address) -> Option<(u8, &'static [u8])> {
+        if !self.is_accepting() {
+            return Some((self.participation_status(), b"Not accepting participants"));
+        }
+        if self.flag(FLAG_PARTICIPATION_PAUSED) {
+            return Some((STATUS_PAUSED, b"Participation paused"));
+        }
+        // Best effort: a contract entering from its constructor has no code yet, and comparing
+        // against tx.origin instead would lock out smart contract wallets
+        if self.flag(FLAG_REQUIRE_EOA) && self.vm().code_size(participant) > 0 {
+            return Some((STATUS_CONTRACT_NOT_ALLOWED, b"Contracts not allowed"));
+        }
+        if self.is_full() {
+            return Some((STATUS_FULL, b"Lottery full"));
+        }
+        if self.participant_position.get(participant) != U256::ZERO {
+            return Some((STATUS_ALREADY_PARTICIPATING, b"Already participating"));
+        }
+        if self.removed_in_round.get(participant) == self.round_number.get() {
+            return Some((STATUS_REMOVED, b"Removed from this round"));
+        }
+        if !self.holds_min_token_balance(participant) {
+            return Some((STATUS_INSUFFICIENT_TOKEN_BALANCE, b"Insufficient token balance"));
+        }
+        None
+    }
+
+    /// Anti-sybil gate; an unset or failing token counts as a zero balance
+    fn holds_min_token_balance(&self, participant: Address) -> bool {
+        let min_balance = self.min_token_balance_to_enter.get();
+        if min_balance == U256::ZERO {
+            return true;
+        }
+        let balance = IERC20::new(self.erc20_token_address.get())
+            .balance_of(self, participant)
+            .unwrap_or(U256::ZERO);
+        balance >= min_balance
+    }
+
+    fn note_payment(&mut self, participant: Address, asset: Address, amount: U256) {
+        self.entry_asset.setter(participant).set(asset);
+        let paid = self.entry_paid.get(participant);
+        self.entry_paid.setter(participant).set(paid + amount);
+        self.current_pool.set(self.current_pool.get() + amount);
+        if asset == Address::ZERO {
+            self.current_native_pool.set(self.current_native_pool.get() + amount);
+        }
+    }
+
+    /// Owe `who` `amount` wei, paid out by `claim_refund`
+    fn queue_refund(&mut self, who: Address, amount: U256) {
+        let pending = self.pending_refunds.get(who);
+        self.pending_refunds.setter(who).set(pending + amount);
+        self.total_pending_refunds.set(self.total_pending_refunds.get() + amount);
+    }
+
+    /// Push an entry refund in `asset`, queueing it for a pull when the push fails
+    fn refund_entry(&mut self, who: Address, asset: Address, amount: U256) {
+        if amount == U256::ZERO {
+            return;
+        }
+        let pushed = if asset == Address::ZERO {
+            self.vm().call(&Call::new().value(amount), who, &[]).is_ok()
+        } else {
+            matches!(IERC20::new(asset).transfer(&mut *self, who, amount), Ok(true))
+        };
+        if !pushed {
+            self.queue_entry_refund(who, asset, amount);
+        }
+    }
+
+    /// Owe `who` an entry refund of `amount` in `asset`, for `claim_refund` or `claim_token_refund`
+    fn queue_entry_refund(&mut self, who: Address, asset: Address, amount: U256) {
+        if asset == Address::ZERO {
+            self.queue_refund(who, amount);
+        } else {
+            let pending = self.pending_token_refunds.getter(asset).get(who);
+            self.pending_token_refunds.setter(asset).setter(who).set(pending + amount);
+            let total = self.total_pending_token_refunds.get(asset);
+            self.total_pending_token_refunds.setter(asset).set(total + amount);
+        }
+    }
+
+    fn record_entry(&mut self, participant: Address) {
+        self.record_entry_with_tickets(participant, U256::from(1));
+    }
+
+    fn record_entry_with_tickets(&mut self, participant: Address, tickets: U256) {
+        self.participants.push(participant);
+        let position = self.participants.len();
+        self.participant_position.setter(participant).set(U256::from(position));
+        self.fenwick_init_node(position);
+        self.add_tickets(participant, position, tickets);
+        let entry_time = U256::from(self.vm().block_timestamp());
+        self.s_entry_time.setter(participant).set(entry_time);
+    }
+
+    fn add_tickets(&mut self, participant: Address, position: usize, tickets: U256) {
+        let count = self.ticket_count.get(participant);
+        self.ticket_count.setter(participant).set(count + tickets);
+        self.total_tickets.set(self.total_tickets.get() + tickets);
+        self.fenwick_add(position, tickets);
+    }
+
+    /// Reset the round: participants plus all ticket bookkeeping
+    fn clear_participants(&mut self) {
+        for position in 1..=self.participants.len() {
+            let participant = self.participants.get(position - 1).unwrap_or(Address::ZERO);
+            self.participant_position.setter(participant).set(U256::ZERO);
+            self.ticket_count.setter(participant).set(U256::ZERO);
+            self.entry_paid.setter(participant).set(U256::ZERO);
+            self.entry_asset.setter(participant).set(Address::ZERO);
+            #[cfg(feature = "ticket-tree")]
+            self.ticket_tree.setter(U256::from(position)).set(U256::ZERO);
+        }
+        self.total_tickets.set(U256::ZERO);
+        self.current_pool.set(U256::ZERO);
+        self.current_native_pool.set(U256::ZERO);
+        self.round_number.set(self.round_number.get() + U256::from(1));
+        while !self.participants.is_empty() {
+            let _ = self.participants.pop();
+        }
+    }
+
+    /// Internal function to pick a winner index where earlier entrants get a slightly higher weight
+    /// Each entrant's tickets are scaled by a factor decaying linearly from BASE + MAX_BONUS (earliest
+    /// entry) to BASE (latest entry)
+    fn weighted_winner_index(&self, random_word: U256) -> usize {
+        let len = self.participants.len();
+        let entrants: Vec<(U256, U256)> = (0..len)
+            .map(|i| {
+                let participant = self.participants.get(i).unwrap_or(Address::ZERO);
+                (self.s_entry_time.get(participant), self.ticket_count.get(participant))
+            })
+            .collect();
+        let earliest = entrants.iter().map(|(t, _)| *t).min().unwrap_or(U256::ZERO);
+        let latest = entrants.iter().map(|(t, _)| *t).max().unwrap_or(U256::ZERO);
+        let span = latest - earliest;
+        if span == U256::ZERO {
+            return self.ticket_winner_index(random_word % self.total_tickets.get());
+        }
+
+        let weights: Vec<U256> = entrants
+            .iter()
+            .map(|(t, tickets)| {
+                *tickets
+                    * (U256::from(ENTRY_BONUS_BASE_WEIGHT)
+                        + U256::from(ENTRY_BONUS_MAX_EXTRA_WEIGHT) * (latest - *t) / span)
+            })
+            .collect();
+        let total: U256 = weights.iter().fold(U256::ZERO, |acc, w| acc + *w);
+
+        let target = random_word % total;
+        let mut cumulative = U256::ZERO;
+        for (i, weight) in weights.iter().enumerate() {
+            cumulative += *weight;
+            if target < cumulative {
+                return i;
+            }
+        }
+        len - 1
+    }
+}
+
+// Ticket lookups backed by the Fenwick tree in `ticket_tree`
+#[cfg(feature = "ticket-tree")]
+impl VrfConsumer {
+    /// Build the node of a newly appended `position` from the prefix sums below it
+    /// Updates stop at the length at the time, so the node never saw tickets added before it existed
+    fn fenwick_init_node(&mut self, position: usize) {
+        let low = position & position.wrapping_neg();
+        let covered = self.fenwick_prefix_sum(position - 1) - self.fenwick_prefix_sum(position - low);
+        self.ticket_tree.setter(U256::from(position)).set(covered);
+    }
+
+    fn fenwick_add(&mut self, position: usize, amount: U256) {
+        let len = self.participants.len();
+        let mut i = position;
+        while i <= len {
+            let key = U256::from(i);
+            let value = self.ticket_tree.get(key);
+            self.ticket_tree.setter(key).set(value + amount);
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    fn fenwick_sub(&mut self, position: usize, amount: U256) {
+        let len = self.participants.len();
+        let mut i = position;
+        while i <= len {
+            let key = U256::from(i);
+            let value = self.ticket_tree.get(key);
+            self.ticket_tree.setter(key).set(value - amount);
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Sum of tickets held by participants at positions 1..=position
+    fn fenwick_prefix_sum(&self, position: usize) -> U256 {
+        let mut sum = U256::ZERO;
+        let mut i = position;
+        while i > 0 {
+            sum += self.ticket_tree.get(U256::from(i));
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
+
+    /// O(log n) descent for the 0-based participant index owning ticket number `target`
+    /// i.e. the first position whose prefix sum exceeds `target`
+    fn ticket_winner_index(&self, target: U256) -> usize {
+        let len = self.participants.len();
+        let mut position = 0usize;
+        let mut remaining = target;
+        let mut step = if len == 0 { 0 } else { 1usize << (usize::BITS - 1 - len.leading_zeros()) };
+        while step > 0 {
+            let next = position + step;
+            if next <= len {
+                let weight = self.ticket_tree.get(U256::from(next));
+                if weight <= remaining {
+                    position = next;
+                    remaining -= weight;
+                }
+            }
+            step >>= 1;
+        }
+        position
+    }
+}
+
+#[cfg(not(feature = "ticket-tree"))]
+impl VrfConsumer {
+    // Without the tree the ticket counts are only kept per participant
+    fn fenwick_init_node(&mut self, _position: usize) {}
+
+    fn fenwick_add(&mut self, _position: usize, _amount: U256) {}
+
+    fn fenwick_sub(&mut self, _position: usize, _amount: U256) {}
+
+    /// Sum of tickets held by participants at positions 1..=position
+    fn fenwick_prefix_sum(&self, position: usize) -> U256 {
+        (0..position.min(self.participants.len())).fold(U256::ZERO, |sum, i| {
+            sum + self.ticket_count.get(self.participants.get(i).unwrap_or(Address::ZERO))
+        })
+    }
+
+    /// O(n) scan for the 0-based participant index owning ticket number `target`
+    fn ticket_winner_index(&self, target: U256) -> usize {
+        let len = self.participants.len();
+        let mut cumulative = U256::ZERO;
+        for i in 0..len {
+            cumulative += self.ticket_count.get(self.participants.get(i).unwrap_or(Address::ZERO));
+            if target < cumulative {
+                return i;
+            }
+        }
+        len
+    }
+}
+
+#[cfg(feature = "signed-actions")]
+impl VrfConsumer {
+    /// Apply an owner setter from its calldata; the signature was already checked by `execute_signed`
+    fn dispatch_signed_action(&mut self, action: &[u8]) -> Result<(), Vec<u8>> {
+        if action.len() < 4 {
+            return Err(b"Unsupported action".to_vec());
+        }
+        let selector: [u8; 4] = action[..4].try_into().unwrap_or_default();
+        let invalid = |_| b"Invalid action args".to_vec();
+        match selector {
+            setLotteryEntryFeeCall::SELECTOR => {
+                let call = setLotteryEntryFeeCall::abi_decode(action, true).map_err(invalid)?;
+                self.ensure_valid_entry_fee(call.fee)?;
+                self.lottery_entry_fee.set(call.fee);
+            }
+            setLotteryIntervalHoursCall::SELECTOR => {
+                let call = setLotteryIntervalHoursCall::abi_decode(action, true).map_err(invalid)?;
+                ensure_min_interval(call.interval_hours)?;
+                self.lottery_interval_hours.set(call.interval_hours);
+            }
+            setKeeperRewardCall::SELECTOR => {
+                let call = setKeeperRewardCall::abi_decode(action, true).map_err(invalid)?;
+                self.keeper_reward.set(call.reward);
+            }
+            setMaxRequestPriceCall::SELECTOR => {
+                let call = setMaxRequestPriceCall::abi_decode(action, true).map_err(invalid)?;
+                self.max_request_price.set(call.max_price);
+            }
+            setMinParticipantsCall::SELECTOR => {
+                let call = setMinParticipantsCall::abi_decode(action, true).map_err(invalid)?;
+                self.min_participants.set(call.min_participants);
+            }
+            setMaxParticipantsCall::SELECTOR => {
+                let call = setMaxParticipantsCall::abi_decode(action, true).map_err(invalid)?;
+                self.max_participants.set(call.max_participants);
+            }
+            _ => return Err(b"Unsupported action".to_vec()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "signed-actions")]
+impl IEip712 for VrfConsumer {
+    const NAME: &'static str = "VrfConsumer";
+    const VERSION: &'static str = "1";
+}
+
+fn ensure_min_interval(interval_hours: U256) -> Result<(), Error> {
+    let min_hours = U256::from(MIN_LOTTERY_INTERVAL_HOURS);
+    if interval_hours < min_hours {
+        return Err(Error::IntervalBelowMinimum(IntervalBelowMinimum { interval_hours, min_hours }));
+    }
+    Ok(())
+}
+
+/// Participant index a draw settles on, `rerolls` places past the `drawn` one and wrapping around
+fn rerolled_index(drawn: usize, rerolls: usize, count: usize) -> usize {
+    (drawn + rerolls).checked_rem(count).unwrap_or(0)
+}
+
+const fn xor_selectors(a: [u8; 32], b: [u8; 32]) -> [u8; 4] {
+    [a[0] ^ b[0], a[1] ^ b[1], a[2] ^ b[2], a[3] ^ b[3]]
+}
+
+// Note: We keep ownership management internal through `ownable`.
+fn get_extra_args_for_native_payment() -> Bytes {
+    // Encode extra args according to VRFV2PlusClient._argsToBytes()
+    // Format: abi.encodeWithSelector(EXTRA_ARGS_V1_TAG, extraArgs)
+    // where EXTRA_ARGS_V1_TAG = bytes4(keccak256("VRF ExtraArgsV1")) = 0x92fd1338
+    let mut extra_args_vec = Vec::new();
+    extra_args_vec.extend_from_slice(&[0x92, 0xfd, 0x13, 0x38]); // EXTRA_ARGS_V1_TAG
+    extra_args_vec.extend_from_slice(&[0x00; 28]); // Padding for struct alignment
+    extra_args_vec.extend_from_slice(&[0x00, 0x00, 0x00, 0x01]); // nativePayment: true
+    extra_args_vec.extend_from_slice(&[0x00; 28]); // Final padding
+    Bytes::from(extra_args_vec)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,2364 @@
+//! Native unit tests on the Stylus `TestVM`
+//!
+//! Draws are driven through `request_random_words_with_seed` (`--features test-mode`) or the wrapper
+//! callback; calls to other contracts are answered by `mock_external`
+
+use super::*;
+use super::Error;
+use stylus_sdk::alloy_primitives::keccak256;
+use stylus_sdk::alloy_sol_types::{sol_data, SolCall, SolType};
+use stylus_sdk::testing::*;
+
+// Native stand-ins for the host imports that code outside `TestVM` links against: `sol_interface!`
+// calls and OpenZeppelin's deprecated `msg::sender`/`evm::log` and EIP-712 domain reads bypass the VM.
+// Calls are answered from `mock_external` (unmocked ones revert) and logged for `external_calls`;
+// those logs are dropped and the ETH sent with them only leaves the contract's balance under
+// `track_call_value`
+mod host_stubs {
+    use super::{Address, TestVM, CONTRACT, U256};
+    use stylus_sdk::stylus_core::host::AccountAccess;
+    use core::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+
+    type Mocks = HashMap<(Address, Vec<u8>), Result<Vec<u8>, Vec<u8>>>;
+
+    std::thread_local! {
+        pub static SENDER: Cell<[u8; 20]> = const { Cell::new([0; 20]) };
+        pub static MOCKS: RefCell<Mocks> = RefCell::new(HashMap::new());
+        pub static CALLS: RefCell<Vec<(Address, Vec<u8>, U256)>> = const { RefCell::new(Vec::new()) };
+        static RETURN_DATA: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+        pub static VALUE_LEDGER: RefCell<Option<(TestVM, U256)>> = const { RefCell::new(None) };
+    }
+
+    /// # Safety
+    /// `contract` must point to 20 bytes and `calldata` to `calldata_len` bytes
+    unsafe fn answer(contract: *const u8, calldata: *const u8, calldata_len: usize, value: U256, outs: *mut usize) -> u8 {
+        let to = Address::from_slice(core::slice::from_raw_parts(contract, 20));
+        let data = core::slice::from_raw_parts(calldata, calldata_len).to_vec();
+        CALLS.with(|calls| calls.borrow_mut().push((to, data.clone(), value)));
+        let result = MOCKS.with(|mocks| mocks.borrow().get(&(to, data)).cloned()).unwrap_or(Err(Vec::new()));
+        let (status, returned) = match result {
+            Ok(returned) => (0, returned),
+            Err(returned) => (1, returned),
+        };
+        if status == 0 && value != U256::ZERO {
+            VALUE_LEDGER.with(|ledger| {
+                if let Some((vm, refund)) = &*ledger.borrow() {
+                    let contract = vm.contract_address();
+                    vm.set_balance(contract, vm.balance(contract) - value + *refund);
+                }
+            });
+        }
+        *outs = returned.len();
+        RETURN_DATA.with(|data| *data.borrow_mut() = returned);
+        status
+    }
+
+    #[no_mangle]
+    extern "C" fn msg_sender(sender: *mut u8) {
+        let bytes = SENDER.with(Cell::get);
+        // SAFETY: the SDK passes a 20-byte output buffer
+        unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), sender, 20) };
+    }
+
+    #[no_mangle]
+    extern "C" fn contract_address(address: *mut u8) {
+        // SAFETY: the SDK passes a 20-byte output buffer
+        unsafe { core::ptr::copy_nonoverlapping(CONTRACT.as_ptr(), address, 20) };
+    }
+
+    #[no_mangle]
+    extern "C" fn chainid() -> u64 {
+        42_161
+    }
+
+    #[no_mangle]
+    extern "C" fn emit_log(_data: *const u8, _len: usize, _topics: usize) {}
+
+    #[no_mangle]
+    extern "C" fn storage_flush_cache(_clear: bool) {}
+
+    #[no_mangle]
+    unsafe extern "C" fn call_contract(
+        contract: *const u8,
+        calldata: *const u8,
+        calldata_len: usize,
+        value: *const u8,
+        _gas: u64,
+        return_data_len: *mut usize,
+    ) -> u8 {
+        let value = U256::from_be_slice(core::slice::from_raw_parts(value, 32));
+        answer(contract, calldata, calldata_len, value, return_data_len)
+    }
+
+    #[no_mangle]
+    extern "C" fn delegate_call_contract(
+        _contract: *const u8,
+        _calldata: *const u8,
+        _calldata_len: usize,
+        _gas: u64,
+        _return_data_len: *mut usize,
+    ) -> u8 {
+        panic!("delegate calls are not supported in tests")
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn static_call_contract(
+        contract: *const u8,
+        calldata: *const u8,
+        calldata_len: usize,
+        _gas: u64,
+        return_data_len: *mut usize,
+    ) -> u8 {
+        answer(contract, calldata, calldata_len, U256::ZERO, return_data_len)
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn read_return_data(dest: *mut u8, offset: usize, size: usize) -> usize {
+        RETURN_DATA.with(|data| {
+            let data = data.borrow();
+            let available = data.get(offset..).unwrap_or_default();
+            let copied = available.len().min(size);
+            core::ptr::copy_nonoverlapping(available.as_ptr(), dest, copied);
+            copied
+        })
+    }
+
+    #[no_mangle]
+    extern "C" fn return_data_size() -> usize {
+        RETURN_DATA.with(|data| data.borrow().len())
+    }
+}
+
+/// Answer calls from the contract to `to` with exactly `calldata`
+fn mock_external(to: Address, calldata: Vec<u8>, result: Result<Vec<u8>, Vec<u8>>) {
+    host_stubs::MOCKS.with(|mocks| mocks.borrow_mut().insert((to, calldata), result));
+}
+
+/// Calls the contract made through `sol_interface!` bindings, as (to, calldata, value)
+fn external_calls() -> Vec<(Address, Vec<u8>, U256)> {
+    host_stubs::CALLS.with(|calls| calls.borrow().clone())
+}
+
+const OWNER: Address = Address::new([0x11; 20]);
+const WRAPPER: Address = Address::new([0x22; 20]);
+const CONTRACT: Address = Address::new([0x33; 20]);
+const ALICE: Address = Address::new([0xa1; 20]);
+const BOB: Address = Address::new([0xb0; 20]);
+const CAROL: Address = Address::new([0xc0; 20]);
+
+const FEE: u64 = 500_000;
+const START: u64 = 1_700_000_000;
+const NAME: &str = "Daily";
+
+const TOKEN: Address = Address::new([0x70; 20]);
+
+// Calldata of the reward token calls the contract makes
+mod token_calls {
+    stylus_sdk::alloy_sol_types::sol! {
+        function decimals() external view returns (uint8);
+        function transferFrom(address from, address to, uint256 amount) external returns (bool);
+        function transfer(address to, uint256 amount) external returns (bool);
+        function burn(uint256 value) external;
+        function balanceOf(address account) external view returns (uint256);
+    }
+}
+
+fn encode_true() -> Vec<u8> {
+    U256::from(1).to_be_bytes::<32>().to_vec()
+}
+
+/// Configure `TOKEN` as the reward token with `decimals`
+fn set_reward_token(vm: &TestVM, contract: &mut VrfConsumer, decimals: u8) {
+    vm.set_code(TOKEN, vec![0]);
+    mock_external(
+        TOKEN,
+        token_calls::decimalsCall {}.abi_encode(),
+        Ok(U256::from(decimals).to_be_bytes::<32>().to_vec()),
+    );
+    act_as(vm, OWNER);
+    contract.set_erc20_token(TOKEN).unwrap();
+}
+
+/// Make `who` the caller for both `TestVM` and the deprecated global `msg::sender`
+fn act_as(vm: &TestVM, who: Address) {
+    vm.set_sender(who);
+    host_stubs::SENDER.with(|sender| sender.set(who.into_array()));
+}
+
+fn setup() -> (TestVM, VrfConsumer) {
+    let vm = TestVM::default();
+    vm.set_contract_address(CONTRACT);
+    vm.set_block_timestamp(START);
+    vm.set_block_number(1);
+    act_as(&vm, OWNER);
+    let mut contract = VrfConsumer::from(&vm);
+    contract.constructor(WRAPPER, OWNER, NAME.into()).unwrap();
+    (vm, contract)
+}
+
+/// Enter `who` with the flat entry fee, crediting the fee to the contract balance as the host would
+fn enter(vm: &TestVM, contract: &mut VrfConsumer, who: Address) {
+    let fee = U256::from(FEE);
+    act_as(vm, who);
+    vm.set_value(fee);
+    vm.set_balance(CONTRACT, vm.balance(CONTRACT) + fee);
+    contract.participate_in_lottery().unwrap();
+    vm.set_value(U256::ZERO);
+}
+
+/// Move past the draw interval so the next draw isn't blocked by timing
+fn advance_past_interval(vm: &TestVM) {
+    vm.set_block_timestamp(vm.block_timestamp() + 5 * 3600);
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn seeded_draw_picks_seed_mod_participants() {
+    let (vm, mut contract) = setup();
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+
+    let request_id = contract.request_random_words_with_seed(U256::from(6)).unwrap();
+
+    // 6 % 3 participants selects the first entrant
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::ZERO, ALICE));
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(3 * FEE));
+    assert!(contract.participants.is_empty());
+    assert!(contract.accepting_participants());
+    assert_eq!(contract.get_last_fulfilled_id(), request_id);
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn seeded_draw_respects_draw_preconditions() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_min_participants(U256::from(2)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    assert_eq!(
+        contract.request_random_words_with_seed(U256::from(1)).unwrap_err(),
+        b"Not enough participants".to_vec()
+    );
+}
+
+#[test]
+fn seeded_draws_are_only_routed_in_test_mode() {
+    use stylus_sdk::abi::Router;
+    let (_vm, mut contract) = setup();
+    let selector = u32::from_be_bytes(keccak256("requestRandomWordsWithSeed(uint256)")[..4].try_into().unwrap());
+    let routed = <VrfConsumer as Router<VrfConsumer>>::route(&mut contract, selector, &encode_u256(U256::from(1)));
+    assert_eq!(routed.is_some(), cfg!(feature = "test-mode"));
+}
+
+fn account(n: u8) -> Address {
+    Address::new([n; 20])
+}
+
+/// Buy `count` tickets for `who`, registering them if they aren't participating yet
+fn buy(vm: &TestVM, contract: &mut VrfConsumer, who: Address, count: u64) {
+    let value = U256::from(FEE * count);
+    act_as(vm, who);
+    vm.set_value(value);
+    vm.set_balance(CONTRACT, vm.balance(CONTRACT) + value);
+    contract.buy_tickets(U256::from(count)).unwrap();
+    vm.set_value(U256::ZERO);
+}
+
+/// Compare every prefix sum and every ticket's owner against a linear scan of the participant list
+fn assert_matches_brute_force(contract: &VrfConsumer) {
+    let (accounts, tickets) = contract.get_participants_with_tickets();
+    let mut cumulative = U256::ZERO;
+    let mut owners = Vec::new();
+    for (i, count) in tickets.iter().enumerate() {
+        cumulative += *count;
+        assert_eq!(contract.get_cumulative_tickets(U256::from(i + 1)), cumulative);
+        owners.extend(core::iter::repeat_n(i, count.to::<usize>()));
+    }
+    assert_eq!(contract.get_total_tickets(), cumulative);
+    for (target, owner) in owners.iter().enumerate() {
+        let idx = contract.ticket_winner_index(U256::from(target));
+        assert_eq!(idx, *owner, "ticket {target} of {accounts:?} with {tickets:?}");
+    }
+}
+
+#[test]
+fn ticket_tree_selects_every_ticket_in_range_with_four_entrants() {
+    let (vm, mut contract) = setup();
+    for who in [ALICE, BOB, CAROL, account(0xd0)] {
+        enter(&vm, &mut contract, who);
+    }
+    assert_matches_brute_force(&contract);
+}
+
+#[test]
+fn ticket_tree_matches_brute_force_under_random_entries_top_ups_and_removals() {
+    let (vm, mut contract) = setup();
+    let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next = |bound: u64| {
+        seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) % bound
+    };
+    let mut joined: Vec<Address> = Vec::new();
+    let mut fresh = 1u8;
+    for _ in 0..200 {
+        match next(4) {
+            0 | 1 if fresh < u8::MAX => {
+                let who = account(fresh);
+                fresh += 1;
+                buy(&vm, &mut contract, who, next(5) + 1);
+                joined.push(who);
+            }
+            2 if !joined.is_empty() => {
+                let who = joined[next(joined.len() as u64) as usize];
+                buy(&vm, &mut contract, who, next(3) + 1);
+            }
+            3 if !joined.is_empty() => {
+                let who = joined.swap_remove(next(joined.len() as u64) as usize);
+                act_as(&vm, OWNER);
+                contract.remove_participant(who).unwrap();
+            }
+            _ => continue,
+        }
+        assert_matches_brute_force(&contract);
+    }
+}
+
+#[test]
+fn prizes_are_scaled_to_the_reward_token_decimals() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    set_reward_token(&vm, &mut contract, 24);
+    assert_eq!(contract.reward_token_decimals(), U256::from(24));
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, U256::from(REQUEST_ID), vec![U256::ZERO]);
+
+    // One wei of entry fee is worth 10^6 units of a 24-decimal token
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(FEE) * U256::from(1_000_000));
+}
+
+#[test]
+fn only_the_native_part_of_the_pool_is_scaled() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    set_reward_token(&vm, &mut contract, 24);
+    enter(&vm, &mut contract, ALICE);
+    let pull = token_calls::transferFromCall { from: BOB, to: CONTRACT, amount: U256::from(FEE) };
+    mock_external(TOKEN, pull.abi_encode(), Ok(encode_true()));
+    act_as(&vm, BOB);
+    contract.participate_with_token().unwrap();
+
+    // ALICE's wei are worth 10^6 token units each, BOB's tokens count as paid
+    let prize = U256::from(FEE) * U256::from(1_000_000) + U256::from(FEE);
+    assert_eq!(contract.effective_winner_prize(), prize);
+    advance_past_interval(&vm);
+    contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, U256::from(REQUEST_ID), vec![U256::ZERO]);
+    assert_eq!(contract.pending_reward_of(ALICE), prize);
+}
+
+#[test]
+fn prizes_keep_their_value_with_6_and_18_decimal_tokens() {
+    // Two whole units of a 6-decimal token
+    let fee = U256::from(2_000_000_000_000u64);
+    for (decimals, expected) in [(6, U256::from(2)), (18, fee)] {
+        let (vm, mut contract) = setup();
+        mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+        set_reward_token(&vm, &mut contract, decimals);
+        contract.set_lottery_entry_fee(fee).unwrap();
+        act_as(&vm, ALICE);
+        vm.set_value(fee);
+        vm.set_balance(CONTRACT, fee);
+        contract.participate_in_lottery().unwrap();
+        vm.set_value(U256::ZERO);
+        advance_past_interval(&vm);
+        contract.request_random_words().unwrap();
+        fulfil(&vm, &mut contract, U256::from(REQUEST_ID), vec![U256::ZERO]);
+
+        assert_eq!(contract.pending_reward_of(ALICE), expected);
+    }
+}
+
+// Calldata of the Chainlink aggregator calls behind `entry_fee_in_native`
+mod feed_calls {
+    stylus_sdk::alloy_sol_types::sol! {
+        function latestAnswer() external view returns (int256);
+        function decimals() external view returns (uint8);
+    }
+}
+
+#[test]
+fn entry_fee_in_native_converts_through_the_price_feed() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_entry_fee_native(U256::from(7)).unwrap();
+    assert_eq!(contract.entry_fee_in_native(), U256::from(7));
+
+    let feed = Address::new([0xfe; 20]);
+    contract.set_price_feed(feed).unwrap();
+    mock_external(
+        feed,
+        feed_calls::decimalsCall {}.abi_encode(),
+        Ok(U256::from(8).to_be_bytes::<32>().to_vec()),
+    );
+    // No usable answer yet, so the stored native fee still applies
+    assert_eq!(contract.entry_fee_in_native(), U256::from(7));
+
+    // 2 native units per fee unit, quoted with 8 decimals
+    let answer = U256::from(200_000_000u64).to_be_bytes::<32>().to_vec();
+    mock_external(feed, feed_calls::latestAnswerCall {}.abi_encode(), Ok(answer));
+    assert_eq!(contract.entry_fee_in_native(), U256::from(2 * FEE));
+}
+
+/// Make plain ETH sends to `who` revert, so refunds and payouts to it are queued
+fn reject_eth(vm: &TestVM, who: Address) {
+    vm.mock_call(who, Vec::new(), Err(Vec::new()));
+}
+
+#[test]
+fn token_entries_burn_their_share_of_the_fee() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    assert!(matches!(contract.set_entry_burn_bps(U256::from(10_001)), Err(Error::InvalidBps(_))));
+    contract.set_entry_burn_bps(U256::from(1_000)).unwrap();
+    let pull = token_calls::transferFromCall { from: ALICE, to: CONTRACT, amount: U256::from(FEE) };
+    mock_external(TOKEN, pull.abi_encode(), Ok(encode_true()));
+    let burn = token_calls::burnCall { value: U256::from(FEE / 10) }.abi_encode();
+    mock_external(TOKEN, burn.clone(), Ok(Vec::new()));
+    act_as(&vm, ALICE);
+    contract.participate_with_token().unwrap();
+
+    assert!(external_calls().contains(&(TOKEN, burn, U256::ZERO)));
+    assert_eq!(contract.participants.get(0), Some(ALICE));
+}
+
+#[test]
+fn tier_entries_pay_the_tier_fee_for_its_weight() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_tier(1, U256::from(4 * FEE), U256::from(10)).unwrap();
+    assert_eq!(contract.get_tier(1), (U256::from(4 * FEE), U256::from(10)));
+
+    act_as(&vm, ALICE);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.participate_in_tier(1).unwrap_err(), b"Wrong amount".to_vec());
+    assert_eq!(contract.participate_in_tier(2).unwrap_err(), b"Tier not configured".to_vec());
+    vm.set_value(U256::from(4 * FEE));
+    contract.participate_in_tier(1).unwrap();
+    enter(&vm, &mut contract, BOB);
+
+    assert_eq!(contract.get_ticket_count(ALICE), U256::from(10));
+    assert_eq!(contract.get_total_tickets(), U256::from(11));
+}
+
+#[test]
+fn tier_entries_add_what_they_paid_to_the_prize_pool() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_tier(1, U256::from(4 * FEE), U256::from(10)).unwrap();
+    act_as(&vm, ALICE);
+    vm.set_value(U256::from(4 * FEE));
+    contract.participate_in_tier(1).unwrap();
+    enter(&vm, &mut contract, BOB);
+
+    // 10 + 1 tickets, but only 4 + 1 fees paid in
+    assert_eq!(contract.effective_winner_prize(), U256::from(5 * FEE));
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    assert_eq!(contract.get_request_pool(request_id).unwrap(), U256::from(5 * FEE));
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(5 * FEE));
+}
+
+#[test]
+fn failed_removal_refunds_are_queued_for_claim_refund() {
+    let (vm, mut contract) = setup();
+    enter(&vm, &mut contract, ALICE);
+    reject_eth(&vm, ALICE);
+    act_as(&vm, OWNER);
+    contract.remove_participant(ALICE).unwrap();
+    assert_eq!(contract.pending_refund_of(ALICE), U256::from(FEE));
+
+    vm.mock_call(ALICE, Vec::new(), Ok(Vec::new()));
+    act_as(&vm, ALICE);
+    contract.claim_refund().unwrap();
+    assert_eq!(contract.pending_refund_of(ALICE), U256::ZERO);
+}
+
+#[test]
+fn removed_token_entrants_are_refunded_in_tokens() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    contract.set_entry_burn_bps(U256::from(1_000)).unwrap();
+    let pull = token_calls::transferFromCall { from: ALICE, to: CONTRACT, amount: U256::from(FEE) };
+    mock_external(TOKEN, pull.abi_encode(), Ok(encode_true()));
+    mock_external(TOKEN, token_calls::burnCall { value: U256::from(FEE / 10) }.abi_encode(), Ok(Vec::new()));
+    act_as(&vm, ALICE);
+    contract.participate_with_token().unwrap();
+    assert_eq!(contract.get_entry_payment(ALICE), (TOKEN, U256::from(FEE - FEE / 10)));
+
+    // The refund transfer is unmocked and reverts, so it is queued; no ETH is sent
+    let refund = token_calls::transferCall { to: ALICE, amount: U256::from(FEE - FEE / 10) }.abi_encode();
+    act_as(&vm, OWNER);
+    contract.remove_participant(ALICE).unwrap();
+    assert_eq!(contract.pending_refund_of(ALICE), U256::ZERO);
+    assert_eq!(contract.pending_token_refund_of(TOKEN, ALICE), U256::from(FEE - FEE / 10));
+    assert_eq!(contract.get_entry_payment(ALICE), (Address::ZERO, U256::ZERO));
+
+    mock_external(TOKEN, refund, Ok(encode_true()));
+    act_as(&vm, ALICE);
+    contract.claim_token_refund(TOKEN).unwrap();
+    assert_eq!(contract.pending_token_refund_of(TOKEN, ALICE), U256::ZERO);
+}
+
+#[test]
+fn first_draw_waits_out_the_grace_period() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_initial_grace_seconds(U256::from(10 * 3600)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+
+    assert!(!contract.can_request_draw());
+    assert_eq!(contract.request_random_words().unwrap_err(), b"Grace period active".to_vec());
+    vm.set_block_timestamp(START + 10 * 3600);
+    assert!(contract.can_request_draw());
+}
+
+#[test]
+fn num_words_is_capped_by_max_num_words() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_max_num_words(U256::from(2)).unwrap();
+
+    assert!(matches!(contract.set_num_words(U256::from(3)), Err(Error::NumWordsAboveMax(_))));
+    assert!(matches!(contract.set_num_words(U256::ZERO), Err(Error::ZeroNumWords(_))));
+    contract.set_num_words(U256::from(2)).unwrap();
+    assert_eq!(contract.num_words(), U256::from(2));
+
+    // The cap can't be lowered below the words already configured
+    assert!(matches!(contract.set_max_num_words(U256::from(1)), Err(Error::NumWordsAboveMax(_))));
+    assert_eq!(contract.max_num_words(), U256::from(2));
+}
+
+#[test]
+fn full_config_reports_every_setting() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_initial_grace_seconds(U256::from(60)).unwrap();
+    contract.set_max_participants(U256::from(50)).unwrap();
+
+    let config = contract.get_full_config();
+    assert_eq!(config.0, U256::from(FEE));
+    assert_eq!(config.1, U256::from(4));
+    assert_eq!(config.2, Address::ZERO);
+    let vrf = (U256::from(100_000), U256::from(3), U256::from(1), U256::from(10));
+    assert_eq!((config.3, config.4, config.5, config.6), vrf);
+    assert_eq!((config.7, config.8, config.9), (U256::from(60), U256::ZERO, U256::from(50)));
+    assert_eq!((config.10, config.11, config.12), (U256::from(10_000), U256::ZERO, U256::ZERO));
+}
+
+#[test]
+fn removal_swaps_the_last_participant_into_the_freed_slot() {
+    let (vm, mut contract) = setup();
+    enter(&vm, &mut contract, ALICE);
+    buy(&vm, &mut contract, BOB, 3);
+    enter(&vm, &mut contract, CAROL);
+
+    act_as(&vm, BOB);
+    assert!(matches!(contract.remove_participant(ALICE), Err(Error::UnauthorizedAccount(_))));
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.remove_participant(OWNER), Err(Error::NotParticipant(_))));
+    contract.remove_participant(ALICE).unwrap();
+
+    let tickets = vec![U256::from(1), U256::from(3)];
+    assert_eq!(contract.get_participants_with_tickets(), (vec![CAROL, BOB], tickets));
+    assert_matches_brute_force(&contract);
+}
+
+#[test]
+fn removed_participants_stay_out_until_the_next_round() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    act_as(&vm, OWNER);
+    contract.remove_participant(ALICE).unwrap();
+
+    act_as(&vm, ALICE);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.participate_in_lottery().unwrap_err(), b"Removed from this round".to_vec());
+    assert_eq!(contract.buy_tickets(U256::from(1)).unwrap_err(), b"Removed from this round".to_vec());
+    vm.set_value(U256::ZERO);
+
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    enter(&vm, &mut contract, ALICE);
+}
+
+// Reward token calls behind prize payouts and the minter probe
+mod reward_calls {
+    stylus_sdk::alloy_sol_types::sol! {
+        function mint(address account, uint256 value) external;
+        function hasRole(bytes32 role, address account) external view returns (bool);
+    }
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn winners_pull_their_prize_with_claim_reward() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    contract.request_random_words_with_seed(U256::from(6)).unwrap();
+
+    let mint = reward_calls::mintCall { account: ALICE, value: U256::from(3 * FEE) }.abi_encode();
+    assert!(!external_calls().iter().any(|(_, data, _)| *data == mint));
+    mock_external(TOKEN, mint.clone(), Ok(Vec::new()));
+    act_as(&vm, ALICE);
+    contract.claim_reward().unwrap();
+    assert!(external_calls().contains(&(TOKEN, mint, U256::ZERO)));
+    assert_eq!(contract.pending_reward_of(ALICE), U256::ZERO);
+}
+
+fn encode_u256(value: U256) -> Vec<u8> {
+    value.to_be_bytes::<32>().to_vec()
+}
+
+const REQUEST_ID: u64 = 77;
+const PRICE: u64 = 1_000;
+
+// Calldata of the VRF wrapper calls behind a draw request
+mod wrapper_calls {
+    stylus_sdk::alloy_sol_types::sol! {
+        function s_configured() external view returns (bool);
+        function s_disabled() external view returns (bool);
+        function calculateRequestPriceNative(uint32 _callbackGasLimit, uint32 _numWords)
+            external
+            view
+            returns (uint256);
+        function requestRandomWordsInNative(
+            uint32 _callbackGasLimit,
+            uint16 _requestConfirmations,
+            uint32 _numWords,
+            bytes extraArgs
+        ) external payable returns (uint256 requestId);
+    }
+}
+
+/// Deploy a configured, native-paying `WRAPPER` that quotes `price` for `num_words` words
+/// and answers the matching request with `request_id`
+fn mock_wrapper(vm: &TestVM, price: u64, request_id: u64, num_words: u32) {
+    vm.set_code(WRAPPER, vec![0]);
+    mock_external(WRAPPER, wrapper_calls::s_configuredCall {}.abi_encode(), Ok(encode_true()));
+    mock_external(WRAPPER, wrapper_calls::s_disabledCall {}.abi_encode(), Ok(encode_u256(U256::ZERO)));
+    let quote = wrapper_calls::calculateRequestPriceNativeCall {
+        _callbackGasLimit: 100_000,
+        _numWords: num_words,
+    };
+    mock_external(WRAPPER, quote.abi_encode(), Ok(encode_u256(U256::from(price))));
+    let request = wrapper_calls::requestRandomWordsInNativeCall {
+        _callbackGasLimit: 100_000,
+        _requestConfirmations: 3,
+        _numWords: num_words,
+        extraArgs: get_extra_args_for_native_payment(),
+    };
+    mock_external(WRAPPER, request.abi_encode(), Ok(encode_u256(U256::from(request_id))));
+}
+
+#[test]
+fn wrapper_support_probe_reports_instead_of_reverting() {
+    let (vm, mut contract) = setup();
+    assert!(!contract.wrapper_supports_native().unwrap());
+
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    assert!(contract.wrapper_supports_native().unwrap());
+    mock_external(WRAPPER, wrapper_calls::s_disabledCall {}.abi_encode(), Ok(encode_true()));
+    assert!(!contract.wrapper_supports_native().unwrap());
+}
+
+#[test]
+fn draws_refuse_a_price_above_the_ceiling() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_max_request_price(U256::from(PRICE - 1)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+
+    assert_eq!(contract.request_random_words().unwrap_err(), b"Price above ceiling".to_vec());
+    act_as(&vm, OWNER);
+    contract.set_max_request_price(U256::from(PRICE)).unwrap();
+    assert_eq!(contract.request_random_words().unwrap(), U256::from(REQUEST_ID));
+}
+
+#[test]
+fn early_entry_bonus_favours_earlier_entrants() {
+    let winner = |bonus: bool| {
+        let (vm, mut contract) = setup();
+        mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+        act_as(&vm, OWNER);
+        contract.set_early_entry_bonus(bonus).unwrap();
+        enter(&vm, &mut contract, ALICE);
+        vm.set_block_timestamp(START + 100);
+        enter(&vm, &mut contract, BOB);
+        advance_past_interval(&vm);
+        contract.request_random_words().unwrap();
+        fulfil(&vm, &mut contract, U256::from(REQUEST_ID), vec![U256::from(101)]);
+        [ALICE, BOB].into_iter().find(|&who| contract.pending_reward_of(who) > U256::ZERO)
+    };
+
+    // Weights 150 and 100 put word 101 on ALICE; uniformly 101 % 2 selects BOB
+    assert_eq!(winner(true), Some(ALICE));
+    assert_eq!(winner(false), Some(BOB));
+}
+
+#[test]
+fn early_entry_bonus_scales_each_entrants_tickets() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_early_entry_bonus(true).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    vm.set_block_timestamp(START + 100);
+    buy(&vm, &mut contract, BOB, 3);
+    advance_past_interval(&vm);
+    contract.request_random_words().unwrap();
+
+    // Weights 150 and 3 x 100 put word 260 on BOB's tickets; by entry time alone it would be ALICE's
+    fulfil(&vm, &mut contract, U256::from(REQUEST_ID), vec![U256::from(260)]);
+    assert!(contract.pending_reward_of(BOB) > U256::ZERO);
+    assert_eq!(contract.pending_reward_of(ALICE), U256::ZERO);
+}
+
+/// Move the ETH sent with successful mocked calls out of the contract's `vm` balance, returning `refund` of it
+fn track_call_value(vm: &TestVM, refund: U256) {
+    host_stubs::VALUE_LEDGER.with(|ledger| *ledger.borrow_mut() = Some((vm.clone(), refund)));
+}
+
+#[test]
+fn actual_paid_is_net_of_the_wrapper_refund() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    track_call_value(&vm, U256::from(300));
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+
+    let request_id = contract.request_random_words().unwrap();
+    assert_eq!(contract.get_actual_paid(request_id), U256::from(PRICE - 300));
+    assert_eq!(vm.balance(CONTRACT), U256::from(FEE + 300 - PRICE));
+}
+
+#[test]
+fn only_the_reward_token_registers_token_entries() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, TOKEN);
+    assert_eq!(contract.register_token_entry(ALICE).unwrap_err(), b"Only reward token".to_vec());
+    set_reward_token(&vm, &mut contract, 18);
+
+    act_as(&vm, ALICE);
+    assert_eq!(contract.register_token_entry(ALICE).unwrap_err(), b"Only reward token".to_vec());
+    act_as(&vm, TOKEN);
+    contract.register_token_entry(ALICE).unwrap();
+    assert_eq!(contract.participants.len(), 1);
+    assert_eq!(contract.participants.get(0), Some(ALICE));
+}
+
+#[test]
+fn can_request_draw_waits_for_the_minimum_participants() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_min_participants(U256::from(2)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+
+    assert!(!contract.can_request_draw());
+    assert_eq!(contract.request_random_words().unwrap_err(), b"Not enough participants".to_vec());
+    enter(&vm, &mut contract, BOB);
+    assert!(contract.can_request_draw());
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn recent_winners_keep_the_newest_twenty_oldest_first() {
+    let (vm, mut contract) = setup();
+    for n in 1..=21u8 {
+        enter(&vm, &mut contract, account(n));
+        advance_past_interval(&vm);
+        contract.request_random_words_with_seed(U256::from(n)).unwrap();
+    }
+
+    let expected: Vec<Address> = (2..=21).map(account).collect();
+    assert_eq!(contract.get_recent_winners(), expected);
+}
+
+/// Deliver `words` for `request_id` as the wrapper
+fn fulfil(vm: &TestVM, contract: &mut VrfConsumer, request_id: U256, words: Vec<U256>) {
+    act_as(vm, WRAPPER);
+    contract.raw_fulfill_random_words(request_id, words).unwrap();
+}
+
+#[test]
+fn fulfillment_latency_runs_from_request_to_callback() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    assert_eq!(contract.get_fulfillment_latency(request_id).unwrap_err(), b"Request not fulfilled".to_vec());
+    assert_eq!(contract.get_fulfillment_latency(U256::from(1)).unwrap_err(), b"Request not found".to_vec());
+    vm.set_block_timestamp(vm.block_timestamp() + 30);
+    fulfil(&vm, &mut contract, request_id, vec![U256::from(5)]);
+    assert_eq!(contract.get_fulfillment_latency(request_id).unwrap(), U256::from(30));
+    assert_eq!(contract.get_average_fulfillment_latency(), U256::from(30));
+}
+
+#[test]
+fn pruning_clears_old_draws_but_keeps_recent_and_pending_ones() {
+    let (vm, mut contract) = setup();
+    for i in 0..3 {
+        mock_wrapper(&vm, PRICE, REQUEST_ID + i, 1);
+        enter(&vm, &mut contract, ALICE);
+        advance_past_interval(&vm);
+        let request_id = contract.request_random_words().unwrap();
+        fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    }
+    act_as(&vm, ALICE);
+    assert!(contract.prune_old_draws().is_err());
+    act_as(&vm, OWNER);
+    contract.set_max_retained_draws(U256::from(1)).unwrap();
+    assert_eq!(contract.prune_old_draws().unwrap(), U256::from(2));
+    for pruned in [REQUEST_ID, REQUEST_ID + 1] {
+        assert_eq!(contract.get_fulfillment_latency(U256::from(pruned)).unwrap_err(), b"Request not found".to_vec());
+    }
+    assert!(contract.get_fulfillment_latency(U256::from(REQUEST_ID + 2)).is_ok());
+
+    // Even with nothing retained, pruning stops at a draw still waiting on its words
+    mock_wrapper(&vm, PRICE, REQUEST_ID + 3, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    let pending = contract.request_random_words().unwrap();
+    act_as(&vm, OWNER);
+    contract.set_max_retained_draws(U256::ZERO).unwrap();
+    assert_eq!(contract.prune_old_draws().unwrap(), U256::from(1));
+    assert_eq!(contract.get_fulfillment_latency(pending).unwrap_err(), b"Request not fulfilled".to_vec());
+    assert_eq!(contract.get_draw_count(), U256::from(4));
+}
+
+#[test]
+fn pruning_keeps_a_deferred_draw_for_finalize_draw() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_max_fulfillment_participants(U256::from(1)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, request_id, vec![U256::from(1)]);
+
+    // Fulfilled but not settled, so nothing is old enough to prune even with nothing retained
+    act_as(&vm, OWNER);
+    assert_eq!(contract.prune_old_draws().unwrap(), U256::ZERO);
+    assert_eq!(contract.finalize_draw().unwrap(), BOB);
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::from(1), BOB));
+}
+
+#[test]
+fn draw_cost_adds_the_keeper_reward_to_the_vrf_price() {
+    let (vm, mut contract) = setup();
+    assert!(contract.estimate_draw_cost().is_err());
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_keeper_reward(U256::from(50)).unwrap();
+
+    assert_eq!(contract.estimate_draw_cost().unwrap(), U256::from(PRICE + 50));
+}
+
+/// Events of type `E` the contract emitted through the VM, oldest first
+fn emitted<E: stylus_sdk::alloy_sol_types::SolEvent>(vm: &TestVM) -> Vec<E> {
+    vm.get_emitted_logs()
+        .iter()
+        .filter_map(|(topics, data)| E::decode_raw_log(topics.iter().copied(), data, true).ok())
+        .collect()
+}
+
+#[test]
+fn received_is_only_logged_from_min_log_value() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_min_log_value(U256::from(100)).unwrap();
+    act_as(&vm, ALICE);
+    vm.set_value(U256::from(99));
+    contract.receive().unwrap();
+    assert!(emitted::<Received>(&vm).is_empty());
+
+    vm.set_value(U256::from(100));
+    contract.receive().unwrap();
+    let received = emitted::<Received>(&vm);
+    assert_eq!((received.len(), received[0].sender, received[0].value), (1, ALICE, U256::from(100)));
+}
+
+#[test]
+fn participation_status_reports_open_paused_and_full() {
+    let (vm, mut contract) = setup();
+    assert_eq!(contract.participation_status(), 0);
+    act_as(&vm, OWNER);
+    contract.set_participation_paused(true).unwrap();
+    assert_eq!(contract.participation_status(), 1);
+    contract.set_participation_paused(false).unwrap();
+    contract.set_max_participants(U256::from(1)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+
+    assert_eq!(contract.participation_status(), 5);
+    act_as(&vm, BOB);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.participate_in_lottery().unwrap_err(), b"Lottery full".to_vec());
+    vm.set_value(U256::ZERO);
+}
+
+// Callback made to `request_for` targets
+mod receiver_calls {
+    stylus_sdk::alloy_sol_types::sol! {
+        function onRandomWords(uint256 request_id, uint256[] random_words) external;
+    }
+}
+
+#[test]
+fn forwarded_requests_deliver_words_without_a_draw() {
+    const TARGET: Address = Address::new([0x5c; 20]);
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    assert!(contract.request_for(TARGET).is_err());
+    act_as(&vm, OWNER);
+    assert_eq!(contract.request_for(Address::ZERO).unwrap_err(), b"Invalid target".to_vec());
+    let request_id = contract.request_for(TARGET).unwrap();
+    assert_eq!(contract.get_forward_target(request_id), TARGET);
+
+    // The target's callback is unmocked and reverts, which must not revert the fulfillment
+    let words = vec![U256::from(9)];
+    fulfil(&vm, &mut contract, request_id, words.clone());
+    let forwarded = receiver_calls::onRandomWordsCall { request_id, random_words: words }.abi_encode();
+    assert!(external_calls().contains(&(TARGET, forwarded, U256::ZERO)));
+    assert_eq!(contract.get_forward_target(request_id), Address::ZERO);
+    assert_eq!(contract.participants.len(), 1);
+    assert_eq!(contract.pending_reward_of(ALICE), U256::ZERO);
+}
+
+#[test]
+fn mintable_requirement_probes_the_minter_role() {
+    let (vm, mut contract) = setup();
+    vm.set_code(TOKEN, vec![0]);
+    act_as(&vm, OWNER);
+    contract.set_require_mintable_token(true).unwrap();
+    assert!(matches!(contract.set_erc20_token(TOKEN), Err(Error::TokenNotMintable(_))));
+
+    let minter = reward_calls::hasRoleCall { role: keccak256(b"MINTER_ROLE"), account: CONTRACT };
+    mock_external(TOKEN, minter.abi_encode(), Ok(encode_true()));
+    contract.set_erc20_token(TOKEN).unwrap();
+    assert!(contract.reward_token_is_mintable());
+}
+
+#[test]
+fn shortfall_covers_the_vrf_request_price() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    assert_eq!(contract.shortfall_for_next_draw().unwrap(), U256::from(PRICE));
+    vm.set_balance(CONTRACT, U256::from(PRICE));
+    assert_eq!(contract.shortfall_for_next_draw().unwrap(), U256::ZERO);
+}
+
+#[test]
+fn price_probe_reports_a_failing_wrapper() {
+    let (vm, mut contract) = setup();
+    assert!(contract.get_request_price().is_err());
+    assert_eq!(contract.try_get_request_price(), (false, U256::ZERO));
+
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    assert_eq!(contract.try_get_request_price(), (true, U256::from(PRICE)));
+}
+
+#[test]
+fn reward_split_is_set_at_once_and_capped_at_the_whole_prize() {
+    let (vm, mut contract) = setup();
+    let split = |winner: u64, treasury: u64, burn: u64| {
+        (U256::from(winner), U256::from(treasury), U256::from(burn))
+    };
+    act_as(&vm, OWNER);
+    let (winner, treasury, burn) = split(6_000, 3_000, 1_001);
+    assert!(matches!(contract.set_reward_split(winner, treasury, burn), Err(Error::InvalidBps(_))));
+    let overflowing = contract.set_reward_split(U256::MAX, U256::from(1), U256::ZERO);
+    assert!(matches!(overflowing, Err(Error::InvalidBps(_))));
+    assert_eq!(contract.get_reward_split(), split(10_000, 0, 0));
+
+    let (winner, treasury, burn) = split(6_000, 3_000, 1_000);
+    contract.set_reward_split(winner, treasury, burn).unwrap();
+    assert_eq!(contract.get_reward_split(), (winner, treasury, burn));
+    act_as(&vm, ALICE);
+    let outcome = contract.set_reward_split(U256::ZERO, U256::ZERO, U256::ZERO);
+    assert!(matches!(outcome, Err(Error::UnauthorizedAccount(_))));
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn verify_draw_reports_the_winner_index_and_the_winner() {
+    let (vm, mut contract) = setup();
+    assert_eq!(contract.verify_draw(U256::from(1)).unwrap_err(), b"Unknown draw".to_vec());
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+
+    let request_id = contract.request_random_words_with_seed(U256::from(7)).unwrap();
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::from(1), BOB));
+}
+
+#[test]
+fn deploy_config_round_trips_the_constructor_args_and_settings() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_lottery_entry_fee(U256::from(2 * FEE)).unwrap();
+    contract.set_min_participants(U256::from(3)).unwrap();
+    contract.set_reward_split(U256::from(6_000), U256::from(3_000), U256::from(1_000)).unwrap();
+
+    let encoded = contract.get_deploy_config();
+    let (wrapper, owner, name, config) =
+        <(Address, Address, String, FullConfig)>::abi_decode_params(&encoded, true).unwrap();
+    assert_eq!((wrapper, owner, name.as_str()), (WRAPPER, OWNER, NAME));
+    assert_eq!(config.abi_encode(), contract.get_full_config().abi_encode());
+    assert_eq!((config.0, config.8, config.10), (U256::from(2 * FEE), U256::from(3), U256::from(6_000)));
+}
+
+#[test]
+fn packed_flags_toggle_independently() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_early_entry_bonus(true).unwrap();
+    contract.set_require_mintable_token(true).unwrap();
+    contract.set_early_entry_bonus(false).unwrap();
+
+    assert!(contract.require_mintable_token() && contract.accepting_participants());
+    assert!(!contract.early_entry_bonus() && !contract.reward_token_is_mintable());
+}
+
+#[cfg(feature = "signed-actions")]
+#[test]
+fn owner_signed_actions_apply_once() {
+    const ECRECOVER: Address = Address::with_last_byte(1);
+    let (vm, mut contract) = setup();
+    let action = setKeeperRewardCall { reward: U256::from(50) }.abi_encode();
+    let (r, s, v) = (B256::repeat_byte(1), B256::repeat_byte(2), 27u8);
+    let signature = [r.as_slice(), s.as_slice(), &[v]].concat();
+    let struct_hash = keccak256([SIGNED_ACTION_TYPEHASH, keccak256(&action).0, [0; 32]].concat());
+    let digest = contract.hash_typed_data_v4(struct_hash);
+    let recover = [digest.as_slice(), &encode_u256(U256::from(v)), r.as_slice(), s.as_slice()].concat();
+    mock_external(ECRECOVER, recover, Ok(OWNER.into_word().to_vec()));
+    act_as(&vm, ALICE);
+
+    let truncated = signature[..64].to_vec().into();
+    assert_eq!(
+        contract.execute_signed(action.clone().into(), U256::ZERO, truncated).unwrap_err(),
+        b"Invalid signature length".to_vec()
+    );
+    // A signature from any other key recovers to a non-owner and is rejected
+    let forged_r = B256::repeat_byte(3);
+    let forged = [forged_r.as_slice(), s.as_slice(), &[v]].concat();
+    let recover_forged = [digest.as_slice(), &encode_u256(U256::from(v)), forged_r.as_slice(), s.as_slice()].concat();
+    mock_external(ECRECOVER, recover_forged, Ok(ALICE.into_word().to_vec()));
+    assert_eq!(
+        contract.execute_signed(action.clone().into(), U256::ZERO, forged.into()).unwrap_err(),
+        b"Signer is not owner".to_vec()
+    );
+    assert_eq!(contract.signed_action_nonce(), U256::ZERO);
+    contract.execute_signed(action.clone().into(), U256::ZERO, signature.clone().into()).unwrap();
+    assert_eq!(contract.keeper_reward(), U256::from(50));
+    assert_eq!(contract.signed_action_nonce(), U256::from(1));
+    assert_eq!(
+        contract.execute_signed(action.into(), U256::ZERO, signature.into()).unwrap_err(),
+        b"Invalid nonce".to_vec()
+    );
+}
+
+#[test]
+fn version_and_contract_type_identify_the_build() {
+    let (_vm, contract) = setup();
+    assert_eq!(contract.version(), env!("CARGO_PKG_VERSION"));
+    assert_eq!(contract.contract_type(), "VrfConsumer");
+}
+
+#[test]
+fn permit_and_participate_enters_even_when_the_permit_fails() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, ALICE);
+    let fee = U256::from(FEE);
+    assert_eq!(
+        contract.permit_and_participate(fee, U256::MAX, 27, B256::ZERO, B256::ZERO).unwrap_err(),
+        b"Token not set".to_vec()
+    );
+    set_reward_token(&vm, &mut contract, 18);
+    let pull = token_calls::transferFromCall { from: ALICE, to: CONTRACT, amount: fee };
+    mock_external(TOKEN, pull.abi_encode(), Ok(encode_true()));
+
+    // The permit itself is unmocked and reverts, as a front-run permit would
+    act_as(&vm, ALICE);
+    contract.permit_and_participate(fee, U256::MAX, 27, B256::ZERO, B256::ZERO).unwrap();
+    assert_eq!(contract.get_participants_with_tickets().0, vec![ALICE]);
+}
+
+#[test]
+fn force_reset_clears_a_stuck_withdraw_lock() {
+    let (vm, mut contract) = setup();
+    contract.set_withdrawing(true);
+    act_as(&vm, ALICE);
+    assert!(matches!(contract.force_reset_withdraw_lock(), Err(Error::UnauthorizedAccount(_))));
+    assert!(contract.is_withdrawing());
+
+    act_as(&vm, OWNER);
+    contract.force_reset_withdraw_lock().unwrap();
+    assert_eq!(emitted::<WithdrawLockReset>(&vm).len(), 1);
+    assert!(!contract.is_withdrawing());
+}
+
+#[test]
+fn participant_pages_clamp_to_the_list() {
+    let (vm, mut contract) = setup();
+    enter(&vm, &mut contract, ALICE);
+    buy(&vm, &mut contract, BOB, 2);
+    enter(&vm, &mut contract, CAROL);
+
+    let page = contract.get_participants_with_tickets_page(U256::from(1), U256::from(5));
+    assert_eq!(page, (vec![BOB, CAROL], vec![U256::from(2), U256::from(1)]));
+    let empty: (Vec<Address>, Vec<U256>) = Default::default();
+    assert_eq!(contract.get_participants_with_tickets_page(U256::from(3), U256::from(1)), empty);
+    assert_eq!(contract.get_participants_with_tickets_page(U256::MAX, U256::MAX), empty);
+}
+
+#[test]
+fn one_entry_per_block_limits_entries_and_top_ups() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_one_entry_per_block(true).unwrap();
+    buy(&vm, &mut contract, ALICE, 1);
+
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.buy_tickets(U256::from(1)).unwrap_err(), b"One entry per block".to_vec());
+    vm.set_block_number(2);
+    contract.buy_tickets(U256::from(1)).unwrap();
+    enter(&vm, &mut contract, BOB);
+}
+
+#[test]
+fn request_fulfilled_carries_every_random_word() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    let words = vec![U256::from(3), U256::from(4)];
+    fulfil(&vm, &mut contract, request_id, words.clone());
+    let fulfilled = emitted::<RequestFulfilled>(&vm);
+    assert_eq!(fulfilled.len(), 1);
+    assert_eq!((fulfilled[0].requestId, fulfilled[0].winner), (request_id, BOB));
+    assert_eq!(fulfilled[0].randomWords, words);
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn auto_compound_rolls_the_unallocated_share_into_the_next_round() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_reward_split(U256::from(5_000), U256::ZERO, U256::ZERO).unwrap();
+    contract.set_auto_compound(true).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    contract.request_random_words_with_seed(U256::from(1)).unwrap();
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(FEE / 2));
+    assert_eq!(contract.carry_over(), U256::from(FEE / 2));
+
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    contract.request_random_words_with_seed(U256::from(1)).unwrap();
+    assert_eq!(contract.pending_reward_of(BOB), U256::from((FEE + FEE / 2) / 2));
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn disabling_auto_compound_keeps_the_carry_over() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_reward_split(U256::from(5_000), U256::ZERO, U256::ZERO).unwrap();
+    contract.set_auto_compound(true).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    contract.request_random_words_with_seed(U256::from(1)).unwrap();
+
+    act_as(&vm, OWNER);
+    contract.set_auto_compound(false).unwrap();
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    contract.request_random_words_with_seed(U256::from(1)).unwrap();
+    assert_eq!(contract.pending_reward_of(BOB), U256::from(FEE / 2));
+    assert_eq!(contract.carry_over(), U256::from(FEE / 2));
+
+    act_as(&vm, OWNER);
+    contract.set_auto_compound(true).unwrap();
+    enter(&vm, &mut contract, CAROL);
+    advance_past_interval(&vm);
+    contract.request_random_words_with_seed(U256::from(1)).unwrap();
+    assert_eq!(contract.pending_reward_of(CAROL), U256::from((FEE + FEE / 2) / 2));
+}
+
+#[test]
+fn time_since_last_draw_counts_from_the_latest_request() {
+    let (vm, mut contract) = setup();
+    assert_eq!(contract.time_since_last_draw(), U256::MAX);
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    contract.request_random_words().unwrap();
+
+    vm.set_block_timestamp(vm.block_timestamp() + 100);
+    assert_eq!(contract.time_since_last_draw(), U256::from(100));
+}
+
+#[test]
+fn fixed_reward_mode_ignores_the_pool_and_split() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_reward_split(U256::from(5_000), U256::ZERO, U256::ZERO).unwrap();
+    contract.set_fixed_reward_amount(U256::from(777)).unwrap();
+    contract.set_fixed_reward_mode(true).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(777));
+}
+
+#[test]
+fn migrated_requests_are_fulfilled_by_the_old_wrapper() {
+    const NEW_WRAPPER: Address = Address::new([0x23; 20]);
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    act_as(&vm, OWNER);
+    let unknown = contract.migrate_pending_request(U256::from(1), WRAPPER);
+    assert!(matches!(unknown, Err(Error::RequestNotPending(_))));
+    contract.set_vrf_wrapper(NEW_WRAPPER).unwrap();
+
+    act_as(&vm, WRAPPER);
+    let words = vec![U256::ZERO];
+    assert!(matches!(
+        contract.raw_fulfill_random_words(request_id, words.clone()),
+        Err(Error::OnlyVRFWrapperCanFulfill(_))
+    ));
+    act_as(&vm, OWNER);
+    contract.migrate_pending_request(request_id, WRAPPER).unwrap();
+    fulfil(&vm, &mut contract, request_id, words);
+    assert_eq!(contract.get_request_wrapper(request_id), Address::ZERO);
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(FEE));
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.migrate_pending_request(request_id, WRAPPER), Err(Error::RequestNotPending(_))));
+}
+
+#[cfg(feature = "test-mode")]
+#[test]
+fn manual_restart_keeps_entries_closed_until_a_new_round() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_auto_restart(false).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    contract.request_random_words_with_seed(U256::from(1)).unwrap();
+
+    assert!(!contract.accepting_participants());
+    act_as(&vm, BOB);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.participate_in_lottery().unwrap_err(), b"Not accepting participants".to_vec());
+    assert!(matches!(contract.start_new_round(), Err(Error::UnauthorizedAccount(_))));
+    act_as(&vm, OWNER);
+    contract.start_new_round().unwrap();
+    enter(&vm, &mut contract, BOB);
+}
+
+#[test]
+fn wrapper_fulfillment_reopens_entries_only_with_auto_restart() {
+    let (vm, mut contract) = setup();
+    assert!(contract.auto_restart());
+    for (auto_restart, request_id) in [(true, REQUEST_ID), (false, REQUEST_ID + 1)] {
+        act_as(&vm, OWNER);
+        contract.set_auto_restart(auto_restart).unwrap();
+        mock_wrapper(&vm, PRICE, request_id, 1);
+        enter(&vm, &mut contract, ALICE);
+        advance_past_interval(&vm);
+        let request_id = contract.request_random_words().unwrap();
+        fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+        assert_eq!(contract.accepting_participants(), auto_restart);
+    }
+
+    act_as(&vm, OWNER);
+    contract.start_new_round().unwrap();
+    enter(&vm, &mut contract, BOB);
+}
+
+#[test]
+fn fulfillment_above_the_participant_threshold_defers_the_winner() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_max_fulfillment_participants(U256::from(1)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    fulfil(&vm, &mut contract, request_id, vec![U256::from(3)]);
+    assert!(contract.draw_deferred());
+    assert_eq!(contract.deferred_draw_id(), request_id);
+    assert_eq!(contract.participation_status(), 2);
+    assert_eq!(contract.get_last_fulfilled_value(), U256::from(3));
+    assert!(emitted::<RequestFulfilled>(&vm).is_empty());
+    assert!(!contract.accepting_participants());
+    assert_eq!(contract.participants.len(), 2);
+
+    act_as(&vm, CAROL);
+    assert_eq!(contract.finalize_draw().unwrap(), BOB);
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::from(1), BOB));
+    assert!(contract.accepting_participants());
+    assert!(!contract.draw_deferred());
+    assert_eq!(contract.participation_status(), 0);
+    assert_eq!(contract.finalize_draw().unwrap_err(), b"No deferred draw".to_vec());
+}
+
+#[test]
+fn burn_pool_burns_held_tokens_up_to_the_balance() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    let balance = token_calls::balanceOfCall { account: CONTRACT }.abi_encode();
+    mock_external(TOKEN, balance, Ok(encode_u256(U256::from(FEE))));
+    let burn = token_calls::burnCall { value: U256::from(FEE / 2) }.abi_encode();
+    mock_external(TOKEN, burn.clone(), Ok(Vec::new()));
+
+    act_as(&vm, ALICE);
+    assert!(matches!(contract.burn_pool(U256::from(FEE / 2)), Err(Error::UnauthorizedAccount(_))));
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.burn_pool(U256::from(FEE + 1)), Err(Error::InsufficientPoolBalance(_))));
+    contract.burn_pool(U256::from(FEE / 2)).unwrap();
+    assert!(external_calls().contains(&(TOKEN, burn, U256::ZERO)));
+}
+
+#[test]
+fn burn_pool_leaves_token_entries_and_refunds_alone() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    for who in [ALICE, BOB] {
+        let pull = token_calls::transferFromCall { from: who, to: CONTRACT, amount: U256::from(FEE) };
+        mock_external(TOKEN, pull.abi_encode(), Ok(encode_true()));
+        act_as(&vm, who);
+        contract.participate_with_token().unwrap();
+    }
+    // BOB's refund transfer is unmocked, so it stays owed
+    act_as(&vm, OWNER);
+    contract.remove_participant(BOB).unwrap();
+    assert_eq!(contract.pending_token_refund_of(TOKEN, BOB), U256::from(FEE));
+    let balance = token_calls::balanceOfCall { account: CONTRACT }.abi_encode();
+    mock_external(TOKEN, balance, Ok(encode_u256(U256::from(3 * FEE))));
+    let burn = token_calls::burnCall { value: U256::from(FEE) }.abi_encode();
+    mock_external(TOKEN, burn.clone(), Ok(Vec::new()));
+
+    assert!(matches!(contract.burn_pool(U256::from(FEE + 1)), Err(Error::InsufficientPoolBalance(_))));
+    contract.burn_pool(U256::from(FEE)).unwrap();
+    assert!(external_calls().contains(&(TOKEN, burn, U256::ZERO)));
+}
+
+#[test]
+fn set_vrf_config_updates_all_three_or_none() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.set_vrf_config(200_000, 5, 11), Err(Error::NumWordsAboveMax(_))));
+    assert!(matches!(contract.set_vrf_config(0, 5, 2), Err(Error::InvalidVrfConfig(_))));
+    assert!(matches!(contract.set_vrf_config(200_000, 201, 2), Err(Error::InvalidVrfConfig(_))));
+    let config = contract.get_full_config();
+    assert_eq!((config.3, config.4, config.5), (U256::from(100_000), U256::from(3), U256::from(1)));
+
+    contract.set_vrf_config(200_000, 5, 2).unwrap();
+    let config = contract.get_full_config();
+    assert_eq!((config.3, config.4, config.5), (U256::from(200_000), U256::from(5), U256::from(2)));
+    let changed = emitted::<VrfConfigChanged>(&vm);
+    assert_eq!(changed.len(), 1);
+    assert_eq!((changed[0].callbackGasLimit, changed[0].requestConfirmations, changed[0].numWords), (200_000, 5, 2));
+}
+
+#[test]
+fn total_value_locked_reports_native_and_token_balances() {
+    let (vm, mut contract) = setup();
+    enter(&vm, &mut contract, ALICE);
+    assert_eq!(contract.total_value_locked().unwrap(), (U256::from(FEE), U256::ZERO));
+
+    set_reward_token(&vm, &mut contract, 18);
+    let pull = token_calls::transferFromCall { from: BOB, to: CONTRACT, amount: U256::from(FEE) };
+    mock_external(TOKEN, pull.abi_encode(), Ok(encode_true()));
+    act_as(&vm, BOB);
+    contract.participate_with_token().unwrap();
+    let balance = token_calls::balanceOfCall { account: CONTRACT }.abi_encode();
+    mock_external(TOKEN, balance, Ok(encode_u256(U256::from(FEE))));
+    assert_eq!(contract.total_value_locked().unwrap(), (U256::from(FEE), U256::from(FEE)));
+}
+
+#[test]
+fn entrants_below_the_minimum_token_balance_are_rejected() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    contract.set_min_token_balance_to_enter(U256::from(100)).unwrap();
+    let alice_balance = token_calls::balanceOfCall { account: ALICE }.abi_encode();
+    mock_external(TOKEN, alice_balance, Ok(encode_u256(U256::from(99))));
+    let bob_balance = token_calls::balanceOfCall { account: BOB }.abi_encode();
+    mock_external(TOKEN, bob_balance, Ok(encode_u256(U256::from(100))));
+
+    act_as(&vm, ALICE);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.participate_in_lottery().unwrap_err(), b"Insufficient token balance".to_vec());
+    enter(&vm, &mut contract, BOB);
+}
+
+#[test]
+fn extra_args_encode_native_payment() {
+    let (_vm, contract) = setup();
+    let mut expected = vec![0x92, 0xfd, 0x13, 0x38];
+    expected.extend_from_slice(&encode_true());
+    expected.extend_from_slice(&[0x00; 28]);
+    assert_eq!(contract.get_extra_args().to_vec(), expected);
+}
+
+#[test]
+fn zero_entry_fee_requires_free_entry_to_be_allowed() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.set_lottery_entry_fee(U256::ZERO), Err(Error::ZeroEntryFee(_))));
+    assert_eq!(contract.lottery_entry_fee(), U256::from(FEE));
+
+    contract.set_free_entry_allowed(true).unwrap();
+    contract.set_lottery_entry_fee(U256::ZERO).unwrap();
+    act_as(&vm, ALICE);
+    contract.participate_in_lottery().unwrap();
+    assert_eq!(contract.participants.get(0), Some(ALICE));
+}
+
+#[test]
+fn request_pool_records_the_prize_pool_at_request_time() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    assert_eq!(contract.get_request_pool(U256::from(REQUEST_ID)).unwrap_err(), b"Request not found".to_vec());
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    assert_eq!(contract.get_request_pool(request_id).unwrap(), U256::from(3 * FEE));
+
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.get_request_pool(request_id).unwrap(), U256::from(3 * FEE));
+}
+
+#[test]
+fn participation_and_draw_pauses_are_independent() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    act_as(&vm, OWNER);
+    contract.set_participation_paused(true).unwrap();
+    act_as(&vm, BOB);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.participate_in_lottery().unwrap_err(), b"Participation paused".to_vec());
+    vm.set_value(U256::ZERO);
+    advance_past_interval(&vm);
+    assert!(contract.can_request_draw());
+
+    act_as(&vm, OWNER);
+    contract.set_participation_paused(false).unwrap();
+    contract.set_draws_paused(true).unwrap();
+    assert_eq!(contract.request_random_words().unwrap_err(), b"Draws paused".to_vec());
+    enter(&vm, &mut contract, BOB);
+}
+
+#[test]
+fn each_request_records_its_own_word_count() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    let first = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, first, vec![U256::ZERO]);
+
+    act_as(&vm, OWNER);
+    contract.set_num_words(U256::from(2)).unwrap();
+    mock_wrapper(&vm, PRICE, REQUEST_ID + 1, 2);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let second = contract.request_random_words().unwrap();
+
+    assert_eq!(contract.get_request_num_words(first).unwrap(), 1);
+    assert_eq!(contract.get_request_num_words(second).unwrap(), 2);
+    assert_eq!(contract.get_request_num_words(U256::from(1)).unwrap_err(), b"Request not found".to_vec());
+}
+
+mod hook_calls {
+    stylus_sdk::alloy_sol_types::sol! {
+        function isEligible(address account) external view returns (bool);
+    }
+}
+
+#[test]
+fn ineligible_winners_pass_the_prize_to_the_next_participant() {
+    const HOOK: Address = Address::new([0x48; 20]);
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_winner_eligibility_hook(HOOK).unwrap();
+    let eligible = |account: Address| hook_calls::isEligibleCall { account }.abi_encode();
+    mock_external(HOOK, eligible(ALICE), Ok(encode_u256(U256::ZERO)));
+    mock_external(HOOK, eligible(BOB), Ok(encode_true()));
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    // Word 0 draws Alice, who the hook rejects
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::from(1), BOB));
+    assert_eq!(contract.pending_reward_of(BOB), U256::from(2 * FEE));
+    assert_eq!(contract.pending_reward_of(ALICE), U256::ZERO);
+}
+
+#[test]
+fn draws_without_participants_revert_before_paying() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_keeper_reward(U256::from(10)).unwrap();
+    vm.set_balance(CONTRACT, U256::from(PRICE));
+    advance_past_interval(&vm);
+
+    assert_eq!(contract.request_random_words().unwrap_err(), b"No participants".to_vec());
+    assert!(external_calls().is_empty());
+    assert_eq!(vm.balance(CONTRACT), U256::from(PRICE));
+}
+
+#[test]
+fn consumer_interface_id_is_the_xor_of_its_selectors() {
+    mod consumer_calls {
+        stylus_sdk::alloy_sol_types::sol! {
+            function requestRandomWords() external returns (uint256);
+            function rawFulfillRandomWords(uint256 request_id, uint256[] random_words) external;
+        }
+    }
+    let draw = consumer_calls::requestRandomWordsCall::SELECTOR;
+    let fulfill = consumer_calls::rawFulfillRandomWordsCall::SELECTOR;
+    let expected: [u8; 4] = core::array::from_fn(|i| draw[i] ^ fulfill[i]);
+    assert_eq!(CONSUMER_INTERFACE_ID, expected);
+
+    let (_vm, contract) = setup();
+    assert!(contract.supports_interface(FixedBytes(expected)));
+    assert!(contract.supports_interface(FixedBytes([0x01, 0xff, 0xc9, 0xa7])));
+    assert!(!contract.supports_interface(FixedBytes([0xff; 4])));
+}
+
+type FulfillParams = (sol_data::Uint<256>, sol_data::Array<sol_data::Uint<256>>);
+
+fn fulfill_selector() -> [u8; 4] {
+    keccak256(b"rawFulfillRandomWords(uint256,uint256[])")[..4].try_into().unwrap()
+}
+
+/// Decode `rawFulfillRandomWords` calldata as the wrapper sends it, like the entrypoint router does
+fn decode_fulfill_calldata(data: &[u8]) -> Result<(U256, Vec<U256>), Vec<u8>> {
+    if data.len() < 4 || data[..4] != fulfill_selector() {
+        return Err(b"Unknown selector".to_vec());
+    }
+    FulfillParams::abi_decode_params(&data[4..], true).map_err(|_| b"Invalid fulfill calldata".to_vec())
+}
+
+#[test]
+fn raw_fulfill_calldata_round_trips_into_a_draw() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    let words = vec![U256::from(1), U256::from(2)];
+    let mut calldata = fulfill_selector().to_vec();
+    calldata.extend(FulfillParams::abi_encode_params(&(request_id, words.clone())));
+    let (decoded_id, decoded_words) = decode_fulfill_calldata(&calldata).unwrap();
+    assert_eq!((decoded_id, &decoded_words), (request_id, &words));
+    fulfil(&vm, &mut contract, decoded_id, decoded_words);
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::from(1), BOB));
+}
+
+#[test]
+fn malformed_fulfill_calldata_is_rejected() {
+    let mut calldata = fulfill_selector().to_vec();
+    calldata.extend(FulfillParams::abi_encode_params(&(U256::from(REQUEST_ID), vec![U256::from(1)])));
+    assert_eq!(decode_fulfill_calldata(&calldata[..calldata.len() - 1]).unwrap_err(), b"Invalid fulfill calldata".to_vec());
+    assert_eq!(decode_fulfill_calldata(&calldata[4..]).unwrap_err(), b"Unknown selector".to_vec());
+    assert_eq!(decode_fulfill_calldata(&[]).unwrap_err(), b"Unknown selector".to_vec());
+}
+
+#[test]
+fn reward_status_flags_an_unset_or_unmintable_token() {
+    let (vm, mut contract) = setup();
+    assert_eq!(contract.reward_status(), (false, false));
+    // `hasRole` is unmocked, so the MINTER_ROLE probe fails
+    set_reward_token(&vm, &mut contract, 18);
+    assert_eq!(contract.reward_status(), (true, false));
+}
+
+#[test]
+fn streamed_prizes_vest_linearly_until_fully_claimed() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    set_reward_token(&vm, &mut contract, 18);
+    contract.set_stream_rewards(true).unwrap();
+    contract.set_reward_stream_duration(U256::from(100)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    let start = U256::from(vm.block_timestamp());
+    assert_eq!(contract.pending_reward_of(ALICE), U256::ZERO);
+    assert_eq!(
+        contract.get_reward_stream(ALICE),
+        (U256::from(FEE), U256::ZERO, start, U256::from(100))
+    );
+
+    vm.set_block_timestamp(vm.block_timestamp() + 25);
+    let quarter = reward_calls::mintCall { account: ALICE, value: U256::from(FEE / 4) }.abi_encode();
+    mock_external(TOKEN, quarter.clone(), Ok(Vec::new()));
+    act_as(&vm, ALICE);
+    contract.claim_streamed_reward().unwrap();
+    assert!(external_calls().contains(&(TOKEN, quarter, U256::ZERO)));
+    assert_eq!(contract.streamed_reward_claimable(ALICE), U256::ZERO);
+
+    vm.set_block_timestamp(vm.block_timestamp() + 500);
+    let rest = reward_calls::mintCall { account: ALICE, value: U256::from(FEE - FEE / 4) }.abi_encode();
+    mock_external(TOKEN, rest.clone(), Ok(Vec::new()));
+    contract.claim_streamed_reward().unwrap();
+    assert!(external_calls().contains(&(TOKEN, rest, U256::ZERO)));
+    assert_eq!(contract.get_reward_stream(ALICE).1, U256::from(FEE));
+    assert_eq!(contract.streamed_reward_claimable(ALICE), U256::ZERO);
+}
+
+#[test]
+fn a_reused_request_id_reverts() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+
+    // The mocked wrapper hands out the same id again
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let duplicate: Vec<u8> = Error::DuplicateRequestId(DuplicateRequestId { request_id }).into();
+    assert_eq!(contract.request_random_words().unwrap_err(), duplicate);
+}
+
+#[test]
+fn effective_winner_prize_applies_the_winner_split_to_the_pool() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_reward_split(U256::from(6_000), U256::from(3_000), U256::from(1_000)).unwrap();
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    assert_eq!(contract.effective_winner_prize(), U256::from(3 * FEE * 6 / 10));
+
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(3 * FEE * 6 / 10));
+}
+
+#[test]
+fn sweep_dust_keeps_the_next_draw_funded() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_keeper_reward(U256::from(10)).unwrap();
+    vm.set_balance(CONTRACT, U256::from(PRICE + 10 + 123));
+    vm.mock_call(CAROL, Vec::new(), Ok(Vec::new()));
+
+    act_as(&vm, ALICE);
+    assert!(contract.sweep_dust(ALICE).is_err());
+    act_as(&vm, OWNER);
+    assert_eq!(contract.sweep_dust(CAROL).unwrap(), U256::from(123));
+    assert!(!contract.is_withdrawing());
+
+    contract.set_withdrawing(true);
+    assert_eq!(contract.sweep_dust(CAROL).unwrap_err(), b"Withdrawal in progress".to_vec());
+}
+
+#[test]
+fn sweep_dust_leaves_the_live_pool_and_queued_refunds() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    reject_eth(&vm, BOB);
+    act_as(&vm, OWNER);
+    contract.remove_participant(BOB).unwrap();
+    assert_eq!(contract.pending_refund_of(BOB), U256::from(FEE));
+    vm.set_balance(CONTRACT, vm.balance(CONTRACT) + U256::from(PRICE + 123));
+    vm.mock_call(CAROL, Vec::new(), Ok(Vec::new()));
+
+    // ALICE's entry fee and BOB's queued refund stay behind
+    assert_eq!(contract.sweep_dust(CAROL).unwrap(), U256::from(123));
+}
+
+#[test]
+fn lottery_name_is_set_at_deployment_and_by_the_owner() {
+    let (vm, mut contract) = setup();
+    assert_eq!(contract.lottery_name(), NAME);
+    act_as(&vm, ALICE);
+    assert!(matches!(contract.set_lottery_name("Weekly".into()), Err(Error::UnauthorizedAccount(_))));
+    act_as(&vm, OWNER);
+    contract.set_lottery_name("Weekly".into()).unwrap();
+    assert_eq!(contract.lottery_name(), "Weekly");
+}
+
+#[test]
+fn did_win_matches_only_the_recorded_winner() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    assert_eq!(contract.did_win(request_id, BOB).unwrap_err(), b"Unknown draw".to_vec());
+
+    fulfil(&vm, &mut contract, request_id, vec![U256::from(1)]);
+    assert!(contract.did_win(request_id, BOB).unwrap());
+    assert!(!contract.did_win(request_id, ALICE).unwrap());
+}
+
+#[test]
+fn request_sent_reports_the_vrf_price_paid() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    track_call_value(&vm, U256::ZERO);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+
+    let request_id = contract.request_random_words().unwrap();
+    let sent = emitted::<RequestSent>(&vm);
+    assert_eq!(sent.len(), 1);
+    assert_eq!((sent[0].requestId, sent[0].numWords, sent[0].payment), (request_id, 1, U256::from(PRICE)));
+}
+
+#[test]
+fn withdraw_batch_sends_native_and_tokens_to_the_owner() {
+    const OTHER_TOKEN: Address = Address::new([0x71; 20]);
+    let (vm, mut contract) = setup();
+    vm.set_balance(CONTRACT, U256::from(FEE));
+    vm.mock_call(OWNER, Vec::new(), Ok(Vec::new()));
+    let first = token_calls::transferCall { to: OWNER, amount: U256::from(5) }.abi_encode();
+    let second = token_calls::transferCall { to: OWNER, amount: U256::from(7) }.abi_encode();
+    mock_external(TOKEN, first.clone(), Ok(encode_true()));
+    mock_external(OTHER_TOKEN, second.clone(), Ok(encode_true()));
+
+    act_as(&vm, OWNER);
+    let tokens = vec![Address::ZERO, TOKEN, OTHER_TOKEN];
+    let amounts = vec![U256::from(FEE), U256::from(5)];
+    assert_eq!(contract.withdraw_batch(tokens.clone(), amounts).unwrap_err(), b"Length mismatch".to_vec());
+    let amounts = vec![U256::from(FEE), U256::from(5), U256::from(7)];
+    contract.withdraw_batch(tokens, amounts).unwrap();
+    assert!(external_calls().contains(&(TOKEN, first, U256::ZERO)));
+    assert!(external_calls().contains(&(OTHER_TOKEN, second, U256::ZERO)));
+    assert!(!contract.is_withdrawing());
+}
+
+#[test]
+fn wrapper_refunds_are_logged_as_vrf_refunds() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, WRAPPER);
+    vm.set_value(U256::from(300));
+    contract.receive().unwrap();
+
+    let refunds = emitted::<VrfRefund>(&vm);
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds[0].amount, U256::from(300));
+    assert!(emitted::<Received>(&vm).is_empty());
+}
+
+#[test]
+fn verify_draw_reproduces_the_credited_winner_after_bonus_and_rerolls() {
+    const HOOK: Address = Address::new([0x48; 20]);
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_early_entry_bonus(true).unwrap();
+    contract.set_winner_eligibility_hook(HOOK).unwrap();
+    let eligible = |account: Address| hook_calls::isEligibleCall { account }.abi_encode();
+    mock_external(HOOK, eligible(BOB), Ok(encode_u256(U256::ZERO)));
+    mock_external(HOOK, eligible(CAROL), Ok(encode_true()));
+    enter(&vm, &mut contract, ALICE);
+    vm.set_block_timestamp(START + 100);
+    buy(&vm, &mut contract, BOB, 3);
+    vm.set_block_timestamp(START + 200);
+    enter(&vm, &mut contract, CAROL);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    // Weights 150, 3 x 125 and 100 put word 260 on BOB; the hook rejects him and the prize re-rolls to CAROL
+    fulfil(&vm, &mut contract, request_id, vec![U256::from(260)]);
+    assert!(contract.pending_reward_of(CAROL) > U256::ZERO);
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::from(2), CAROL));
+    assert_eq!(contract.get_winner_index(request_id).unwrap(), U256::from(2));
+}
+
+#[test]
+fn winner_index_is_the_word_modulo_participants() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    assert_eq!(contract.get_winner_index(request_id).unwrap_err(), b"Unknown draw".to_vec());
+
+    let word = U256::from(11);
+    fulfil(&vm, &mut contract, request_id, vec![word]);
+    assert_eq!(contract.get_winner_index(request_id).unwrap(), word % U256::from(3));
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (U256::from(2), CAROL));
+}
+
+#[test]
+fn reward_token_must_be_a_contract() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.set_erc20_token(ALICE), Err(Error::TokenNotContract(_))));
+    set_reward_token(&vm, &mut contract, 18);
+    assert_eq!(contract.erc20_token_address(), TOKEN);
+    contract.set_erc20_token(Address::ZERO).unwrap();
+    assert_eq!(contract.erc20_token_address(), Address::ZERO);
+}
+
+#[test]
+fn auto_withdraw_credits_the_operational_cut_to_the_treasury() {
+    const TREASURY: Address = Address::new([0x7e; 20]);
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.set_auto_withdraw_bps(U256::from(1_000)), Err(Error::InvalidBps(_))));
+    contract.set_reward_split(U256::from(9_000), U256::ZERO, U256::ZERO).unwrap();
+    contract.set_auto_withdraw_bps(U256::from(1_000)).unwrap();
+    assert!(matches!(
+        contract.set_reward_split(U256::from(9_001), U256::ZERO, U256::ZERO),
+        Err(Error::InvalidBps(_))
+    ));
+    contract.set_treasury(TREASURY).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    // Nothing is sent to the treasury inside the callback
+    reject_eth(&vm, TREASURY);
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.total_auto_withdrawn(), U256::from(2 * FEE / 10));
+    assert_eq!(contract.pending_refund_of(TREASURY), U256::from(2 * FEE / 10));
+
+    vm.mock_call(TREASURY, Vec::new(), Ok(Vec::new()));
+    act_as(&vm, TREASURY);
+    contract.claim_refund().unwrap();
+    assert_eq!(contract.pending_refund_of(TREASURY), U256::ZERO);
+}
+
+#[test]
+fn auto_withdraw_waits_for_a_draw_with_a_winner() {
+    const TREASURY: Address = Address::new([0x7e; 20]);
+    const HOOK: Address = Address::new([0x48; 20]);
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_reward_split(U256::from(9_000), U256::ZERO, U256::ZERO).unwrap();
+    contract.set_auto_withdraw_bps(U256::from(1_000)).unwrap();
+    contract.set_treasury(TREASURY).unwrap();
+    contract.set_winner_eligibility_hook(HOOK).unwrap();
+    let eligible = |account: Address| hook_calls::isEligibleCall { account }.abi_encode();
+    for who in [ALICE, BOB] {
+        mock_external(HOOK, eligible(who), Ok(encode_u256(U256::ZERO)));
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    // The hook rejects everyone, so the round carries on with its pool untouched
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.verify_draw(request_id).unwrap().1, Address::ZERO);
+    assert_eq!(contract.total_auto_withdrawn(), U256::ZERO);
+    assert_eq!(contract.pending_refund_of(TREASURY), U256::ZERO);
+}
+
+#[test]
+fn participation_quote_explains_why_an_account_cannot_enter() {
+    let (vm, mut contract) = setup();
+    let fee = U256::from(FEE);
+    assert_eq!(contract.participation_quote(ALICE), (true, fee, 0));
+    enter(&vm, &mut contract, ALICE);
+    assert_eq!(contract.participation_quote(ALICE), (false, fee, 6));
+
+    act_as(&vm, OWNER);
+    contract.set_max_participants(U256::from(1)).unwrap();
+    assert_eq!(contract.participation_quote(BOB), (false, fee, 5));
+    contract.set_participation_paused(true).unwrap();
+    assert_eq!(contract.participation_quote(BOB), (false, fee, 1));
+    contract.set_participation_paused(false).unwrap();
+    contract.set_max_participants(U256::ZERO).unwrap();
+
+    contract.set_min_token_balance_to_enter(U256::from(1)).unwrap();
+    assert_eq!(contract.participation_quote(BOB), (false, fee, 7));
+}
+
+#[test]
+fn participation_quote_applies_the_entry_checks() {
+    const BOT: Address = Address::new([0xb7; 20]);
+    let (vm, mut contract) = setup();
+    let fee = U256::from(FEE);
+    act_as(&vm, OWNER);
+    contract.set_require_eoa(true).unwrap();
+    vm.set_code(BOT, vec![0]);
+    assert_eq!(contract.participation_quote(BOT), (false, fee, 8));
+
+    enter(&vm, &mut contract, ALICE);
+    act_as(&vm, OWNER);
+    contract.remove_participant(ALICE).unwrap();
+    assert_eq!(contract.participation_quote(ALICE), (false, fee, 9));
+
+    contract.set_one_entry_per_block(true).unwrap();
+    enter(&vm, &mut contract, BOB);
+    act_as(&vm, OWNER);
+    contract.reset_lottery().unwrap();
+    assert_eq!(contract.participation_quote(BOB), (false, fee, 11));
+
+    contract.set_entry_cooldown_seconds(U256::from(60)).unwrap();
+    vm.set_block_number(vm.block_number() + 1);
+    assert_eq!(contract.participation_quote(BOB), (false, fee, 10));
+    vm.set_block_timestamp(vm.block_timestamp() + 60);
+    assert_eq!(contract.participation_quote(BOB), (true, fee, 0));
+}
+
+#[test]
+fn reset_lottery_waits_for_the_pending_draw_then_clears_the_round() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.reset_lottery(), Err(Error::DrawPending(_))));
+
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    enter(&vm, &mut contract, BOB);
+    act_as(&vm, ALICE);
+    assert!(matches!(contract.reset_lottery(), Err(Error::UnauthorizedAccount(_))));
+    act_as(&vm, OWNER);
+    contract.reset_lottery().unwrap();
+    assert!(contract.participants.is_empty());
+    assert_eq!(contract.get_total_tickets(), U256::ZERO);
+    // BOB's entry fee is owed back rather than kept
+    assert_eq!(contract.pending_refund_of(BOB), U256::from(FEE));
+    assert_eq!(contract.pending_refund_of(ALICE), U256::ZERO);
+    assert!(contract.accepting_participants());
+    assert_eq!(contract.time_since_last_draw(), U256::MAX);
+    assert_eq!(emitted::<LotteryReset>(&vm).len(), 1);
+    enter(&vm, &mut contract, BOB);
+}
+
+/// `word` hashed with the current block, as `set_mix_block_data` does at fulfillment
+fn mixed_with_block(vm: &TestVM, word: U256) -> U256 {
+    let preimage = [
+        word.to_be_bytes::<32>(),
+        U256::from(vm.block_number()).to_be_bytes::<32>(),
+        U256::from(vm.block_timestamp()).to_be_bytes::<32>(),
+    ]
+    .concat();
+    U256::from_be_bytes(keccak256(preimage).0)
+}
+
+#[test]
+fn mixed_draws_pick_the_winner_from_the_hashed_word() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_mix_block_data(true).unwrap();
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    let word = U256::from(9);
+    let mixed = mixed_with_block(&vm, word);
+    assert_ne!(mixed, word);
+    fulfil(&vm, &mut contract, request_id, vec![word]);
+
+    let index = mixed % U256::from(3);
+    let winner = [ALICE, BOB, CAROL][index.to::<usize>()];
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (index, winner));
+    assert_eq!(contract.get_last_fulfilled_value(), word);
+}
+
+#[test]
+fn mixed_draws_log_every_word_unmixed() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_mix_block_data(true).unwrap();
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+
+    // Only the first word is mixed to pick the winner; all 200 reach the event as delivered
+    let words: Vec<U256> = (0..200u64).map(|i| U256::from(i * 7 + 9)).collect();
+    let index = mixed_with_block(&vm, words[0]) % U256::from(3);
+    let winner = [ALICE, BOB, CAROL][index.to::<usize>()];
+    fulfil(&vm, &mut contract, request_id, words.clone());
+    let fulfilled = emitted::<RequestFulfilled>(&vm);
+    assert_eq!((fulfilled.len(), fulfilled[0].winner), (1, winner));
+    assert_eq!(fulfilled[0].randomWords, words);
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (index, winner));
+}
+
+#[test]
+fn deferred_mixed_draws_use_the_block_of_the_callback() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_mix_block_data(true).unwrap();
+    contract.set_max_fulfillment_participants(U256::from(2)).unwrap();
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    let word = U256::from(9);
+    let mixed = mixed_with_block(&vm, word);
+    fulfil(&vm, &mut contract, request_id, vec![word]);
+
+    // Finalizing in a later block doesn't change the outcome
+    vm.set_block_number(vm.block_number() + 7);
+    vm.set_block_timestamp(vm.block_timestamp() + 60);
+    act_as(&vm, CAROL);
+    let index = mixed % U256::from(3);
+    let winner = [ALICE, BOB, CAROL][index.to::<usize>()];
+    assert_eq!(contract.finalize_draw().unwrap(), winner);
+    assert_eq!(contract.verify_draw(request_id).unwrap(), (index, winner));
+}
+
+#[test]
+fn participants_joined_since_counts_current_round_entries() {
+    let (vm, mut contract) = setup();
+    enter(&vm, &mut contract, ALICE);
+    vm.set_block_timestamp(START + 100);
+    enter(&vm, &mut contract, BOB);
+    vm.set_block_timestamp(START + 200);
+    enter(&vm, &mut contract, CAROL);
+
+    assert_eq!(contract.current_round_participant_count(), U256::from(3));
+    assert_eq!(contract.participants_joined_since(U256::from(START)), U256::from(3));
+    assert_eq!(contract.participants_joined_since(U256::from(START + 100)), U256::from(2));
+    assert_eq!(contract.participants_joined_since(U256::from(START + 201)), U256::ZERO);
+}
+
+#[test]
+fn native_deposits_stop_at_the_balance_cap() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_max_contract_balance(U256::from(2 * FEE)).unwrap();
+    enter(&vm, &mut contract, ALICE);
+    enter(&vm, &mut contract, BOB);
+
+    act_as(&vm, CAROL);
+    vm.set_value(U256::from(FEE));
+    vm.set_balance(CONTRACT, U256::from(3 * FEE));
+    assert_eq!(contract.participate_in_lottery().unwrap_err(), b"Balance cap reached".to_vec());
+    assert_eq!(contract.receive().unwrap_err(), b"Balance cap reached".to_vec());
+    act_as(&vm, WRAPPER);
+    contract.receive().unwrap();
+}
+
+#[test]
+fn draw_completed_records_the_whole_draw() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    act_as(&vm, OWNER);
+    contract.set_reward_split(U256::from(8_000), U256::ZERO, U256::ZERO).unwrap();
+    for who in [ALICE, BOB, CAROL] {
+        enter(&vm, &mut contract, who);
+    }
+    advance_past_interval(&vm);
+    let request_id = contract.request_random_words().unwrap();
+    fulfil(&vm, &mut contract, request_id, vec![U256::from(4)]);
+
+    let completed = emitted::<DrawCompleted>(&vm);
+    assert_eq!(completed.len(), 1);
+    let draw = &completed[0];
+    assert_eq!((draw.requestId, draw.winner, draw.winnerIndex), (request_id, BOB, U256::from(1)));
+    assert_eq!((draw.randomWord, draw.participantCount), (U256::from(4), U256::from(3)));
+    assert_eq!(draw.prize, U256::from(3 * FEE * 8 / 10));
+    assert_eq!(contract.pending_reward_of(BOB), draw.prize);
+}
+
+#[test]
+fn repeat_entries_wait_out_the_cooldown() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_entry_cooldown_seconds(U256::from(60)).unwrap();
+    buy(&vm, &mut contract, ALICE, 1);
+
+    vm.set_block_timestamp(START + 59);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.buy_tickets(U256::from(1)).unwrap_err(), b"Cooldown active".to_vec());
+    vm.set_block_timestamp(START + 60);
+    contract.buy_tickets(U256::from(1)).unwrap();
+    assert_eq!(contract.get_ticket_count(ALICE), U256::from(2));
+}
+
+#[test]
+fn merkle_prizes_are_claimed_once_with_a_valid_proof() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    let leaf = |account: Address, amount: u64| {
+        keccak256([account.as_slice(), &U256::from(amount).to_be_bytes::<32>()].concat())
+    };
+    let (alice_leaf, bob_leaf) = (leaf(ALICE, 100), leaf(BOB, 200));
+    let (low, high) = if alice_leaf <= bob_leaf { (alice_leaf, bob_leaf) } else { (bob_leaf, alice_leaf) };
+    let root = keccak256([low.0, high.0].concat());
+    let request_id = U256::from(REQUEST_ID);
+
+    act_as(&vm, ALICE);
+    assert_eq!(
+        contract.claim_merkle_prize(request_id, U256::from(100), vec![bob_leaf]).unwrap_err(),
+        b"No prize root".to_vec()
+    );
+    act_as(&vm, OWNER);
+    contract.set_prize_merkle_root(request_id, root).unwrap();
+
+    let mint = reward_calls::mintCall { account: ALICE, value: U256::from(100) }.abi_encode();
+    mock_external(TOKEN, mint.clone(), Ok(Vec::new()));
+    act_as(&vm, ALICE);
+    contract.claim_merkle_prize(request_id, U256::from(100), vec![bob_leaf]).unwrap();
+    assert!(external_calls().contains(&(TOKEN, mint, U256::ZERO)));
+    assert!(contract.merkle_prize_claimed(request_id, ALICE));
+    assert_eq!(
+        contract.claim_merkle_prize(request_id, U256::from(100), vec![bob_leaf]).unwrap_err(),
+        b"Already claimed".to_vec()
+    );
+
+    act_as(&vm, BOB);
+    assert_eq!(
+        contract.claim_merkle_prize(request_id, U256::from(300), vec![alice_leaf]).unwrap_err(),
+        b"Invalid proof".to_vec()
+    );
+}
+
+#[test]
+fn lottery_interval_respects_the_floor() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    assert!(matches!(contract.set_lottery_interval_hours(U256::ZERO), Err(Error::IntervalBelowMinimum(_))));
+    assert_eq!(contract.lottery_interval_hours(), U256::from(4));
+    contract.set_lottery_interval_hours(U256::from(1)).unwrap();
+    assert_eq!(contract.lottery_interval_hours(), U256::from(1));
+}
+
+#[test]
+fn fulfillment_gas_estimate_scales_with_participants() {
+    let (vm, mut contract) = setup();
+    let empty = contract.estimate_fulfillment_gas();
+    enter(&vm, &mut contract, ALICE);
+    let one = contract.estimate_fulfillment_gas();
+    enter(&vm, &mut contract, BOB);
+    let two = contract.estimate_fulfillment_gas();
+    assert!(empty < one);
+    assert_eq!(two - one, one - empty);
+}
+
+#[test]
+fn underfunded_draws_name_the_missing_balance() {
+    let (vm, mut contract) = setup();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+    vm.set_balance(CONTRACT, U256::from(PRICE - 1));
+    assert_eq!(
+        contract.request_random_words().unwrap_err(),
+        b"Insufficient contract balance for VRF".to_vec()
+    );
+    vm.set_balance(CONTRACT, U256::from(PRICE));
+    contract.request_random_words().unwrap();
+}
+
+#[test]
+fn lifetime_winnings_accumulate_across_draws() {
+    let (vm, mut contract) = setup();
+    for (round, request_id) in [REQUEST_ID, REQUEST_ID + 1].into_iter().enumerate() {
+        mock_wrapper(&vm, PRICE, request_id, 1);
+        enter(&vm, &mut contract, ALICE);
+        if round == 1 {
+            enter(&vm, &mut contract, BOB);
+        }
+        advance_past_interval(&vm);
+        let request_id = contract.request_random_words().unwrap();
+        fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    }
+    assert_eq!(contract.total_won_by(ALICE), U256::from(3 * FEE));
+    assert_eq!(contract.win_count_of(ALICE), U256::from(2));
+    assert_eq!(contract.win_count_of(BOB), U256::ZERO);
+}
+
+#[test]
+fn contract_entrants_are_rejected_when_eoas_are_required() {
+    const BOT: Address = Address::new([0xb7; 20]);
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_require_eoa(true).unwrap();
+    vm.set_code(BOT, vec![0]);
+
+    act_as(&vm, BOT);
+    vm.set_value(U256::from(FEE));
+    assert_eq!(contract.participate_in_lottery().unwrap_err(), b"Contracts not allowed".to_vec());
+    enter(&vm, &mut contract, ALICE);
+}
+
+#[test]
+fn prize_range_spans_one_entrant_to_the_cap() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_reward_split(U256::from(5_000), U256::ZERO, U256::ZERO).unwrap();
+    assert_eq!(contract.prize_range(), (U256::from(FEE / 2), U256::MAX));
+    contract.set_max_participants(U256::from(10)).unwrap();
+    assert_eq!(contract.prize_range(), (U256::from(FEE / 2), U256::from(10 * FEE / 2)));
+}
+
+#[test]
+fn native_payout_mode_credits_eth_instead_of_tokens() {
+    let (vm, mut contract) = setup();
+    for (native, request_id) in [(false, REQUEST_ID), (true, REQUEST_ID + 1)] {
+        act_as(&vm, OWNER);
+        contract.set_pay_winner_native(native).unwrap();
+        mock_wrapper(&vm, PRICE, request_id, 1);
+        enter(&vm, &mut contract, ALICE);
+        advance_past_interval(&vm);
+        let request_id = contract.request_random_words().unwrap();
+        fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    }
+    // The token-mode round left a mintable reward, the native one an ETH claim
+    assert_eq!(contract.pending_reward_of(ALICE), U256::from(FEE));
+    assert_eq!(contract.pending_refund_of(ALICE), U256::from(FEE));
+    assert_eq!(contract.win_count_of(ALICE), U256::from(2));
+
+    vm.mock_call(ALICE, Vec::new(), Ok(Vec::new()));
+    act_as(&vm, ALICE);
+    contract.claim_refund().unwrap();
+    assert_eq!(contract.pending_refund_of(ALICE), U256::ZERO);
+}
+
+#[test]
+fn native_prize_only_counts_eth_paid_in() {
+    let (vm, mut contract) = setup();
+    set_reward_token(&vm, &mut contract, 18);
+    contract.set_pay_winner_native(true).unwrap();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    let pull = token_calls::transferFromCall { from: BOB, to: CONTRACT, amount: U256::from(FEE) };
+    mock_external(TOKEN, pull.abi_encode(), Ok(encode_true()));
+    act_as(&vm, BOB);
+    contract.participate_with_token().unwrap();
+    advance_past_interval(&vm);
+    assert_eq!(contract.effective_winner_prize(), U256::from(FEE));
+    vm.set_balance(CONTRACT, vm.balance(CONTRACT) + U256::from(PRICE));
+    let request_id = contract.request_random_words().unwrap();
+
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.pending_refund_of(ALICE), U256::from(FEE));
+    assert_eq!(contract.pending_reward_of(ALICE), U256::ZERO);
+    assert_eq!(emitted::<DrawCompleted>(&vm)[0].prize, U256::from(FEE));
+}
+
+#[test]
+fn native_prizes_must_be_funded_beyond_the_vrf_fee() {
+    let (vm, mut contract) = setup();
+    act_as(&vm, OWNER);
+    contract.set_pay_winner_native(true).unwrap();
+    contract.set_fixed_reward_amount(U256::from(777)).unwrap();
+    contract.set_fixed_reward_mode(true).unwrap();
+    mock_wrapper(&vm, PRICE, REQUEST_ID, 1);
+    enter(&vm, &mut contract, ALICE);
+    advance_past_interval(&vm);
+
+    // Paying the VRF fee out of the entry would leave the prize short
+    assert_eq!(contract.request_random_words().unwrap_err(), b"Native prize unfunded".to_vec());
+    vm.set_balance(CONTRACT, vm.balance(CONTRACT) + U256::from(PRICE));
+    let request_id = contract.request_random_words().unwrap();
+
+    // Native mode wins over the fixed token reward
+    fulfil(&vm, &mut contract, request_id, vec![U256::ZERO]);
+    assert_eq!(contract.pending_refund_of(ALICE), U256::from(FEE));
+    assert_eq!(contract.pending_reward_of(ALICE), U256::ZERO);
+}
+
+#[test]
+fn native_withdrawals_keep_the_operational_floor() {
+    let (vm, mut contract) = setup();
+    vm.set_balance(CONTRACT, U256::from(FEE));
+    vm.mock_call(OWNER, Vec::new(), Ok(Vec::new()));
+    act_as(&vm, OWNER);
+    contract.set_min_operational_balance(U256::from(FEE / 2)).unwrap();
+
+    let breach = contract.withdraw_batch(vec![Address::ZERO], vec![U256::from(FEE / 2 + 1)]);
+    assert_eq!(breach.unwrap_err(), b"Below operational balance".to_vec());
+    assert!(!contract.is_withdrawing());
+    contract.withdraw_batch(vec![Address::ZERO], vec![U256::from(FEE / 2)]).unwrap();
+}
+
+#[test]
+fn request_counts_report_total_and_fulfilled_draws() {
+    let (vm, mut contract) = setup();
+    assert_eq!(contract.request_counts(), (U256::ZERO, U256::ZERO));
+    for request_id in [REQUEST_ID, REQUEST_ID + 1] {
+        mock_wrapper(&vm, PRICE, request_id, 1);
+        enter(&vm, &mut contract, ALICE);
+        advance_past_interval(&vm);
+        contract.request_random_words().unwrap();
+        if request_id == REQUEST_ID {
+            fulfil(&vm, &mut contract, U256::from(request_id), vec![U256::ZERO]);
+        }
+    }
+    assert_eq!(contract.request_counts(), (U256::from(2), U256::from(1)));
+}